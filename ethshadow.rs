@@ -1,45 +1,209 @@
-use clap::{arg, command, value_parser};
-use color_eyre::eyre::WrapErr;
+use clap::{arg, command, value_parser, ArgAction, ArgMatches, Command};
+use color_eyre::eyre::{bail, WrapErr};
 use color_eyre::Result;
 use env_logger::Env;
+use ethshadow::analysis::{analyze, ANALYSIS_DIR};
+use ethshadow::config::{json_schema, load};
+use ethshadow::export::{export, ExportOptions};
 use ethshadow::generate;
+use ethshadow::grafana::{generate_grafana, COMPOSE_FILE, GRAFANA_DIR};
+use ethshadow::remote_write::receive;
+use ethshadow::run::{supervise, SUMMARY_FILE};
+use ethshadow::sweep::{generate_sweep, MANIFEST_FILE};
 use std::env;
-use std::fs::File;
+use std::fs::write;
 use std::os::unix::prelude::CommandExt;
 use std::path::PathBuf;
+use std::time::Duration;
 
 fn main() -> Result<()> {
     let matches = command!()
         .bin_name("ethshadow")
+        .args_conflicts_with_subcommands(true)
+        .subcommand_negates_reqs(true)
         .arg(arg!(dir: -d [DIR] "Output directory for ethshadow and Shadow")
             .value_parser(value_parser!(PathBuf))
             .default_value("data"))
         .arg(arg!(genonly: --"gen-only" "Generate data dir only, do not invoke Shadow"))
+        .arg(arg!(supervise: --supervise "Keep running while Shadow runs, report progress and write a run summary")
+            .conflicts_with("genonly"))
         .arg(arg!(config: <CONFIG> "Configuration file. See CONFIG.md")
             .value_parser(value_parser!(PathBuf)))
         .arg(arg!(shadow_cli: [SHADOW_CLI_OPTION]... "Optional options passed on to Shadow, except \"-d\" and the config")
             .last(true))
+        .subcommand(Command::new("schema")
+            .about("Print a JSON schema of the configuration file")
+            .arg(arg!(output: -o [FILE] "Write the schema to this file instead of stdout")
+                .value_parser(value_parser!(PathBuf))))
+        .subcommand(Command::new("analyze")
+            .about("Analyze the client logs of a finished simulation")
+            .arg(arg!(dir: [DIR] "Data directory of the simulation")
+                .value_parser(value_parser!(PathBuf))
+                .default_value("data")))
+        .subcommand(Command::new("export")
+            .about("Export metrics captured by Prometheus during the simulation to CSV")
+            .arg(arg!(dir: [DIR] "Data directory of the simulation")
+                .value_parser(value_parser!(PathBuf))
+                .default_value("data"))
+            .arg(arg!(query: -q --query <QUERY> "PromQL query to export, may be given multiple times")
+                .action(ArgAction::Append)
+                .required(true))
+            .arg(arg!(step: --step [SECONDS] "Resolution of the exported series in seconds")
+                .value_parser(value_parser!(u64))
+                .default_value("15"))
+            .arg(arg!(output: -o [OUTPUT] "Output directory, defaults to \"metrics\" in the data directory")
+                .value_parser(value_parser!(PathBuf)))
+            .arg(arg!(prometheus: --prometheus [EXECUTABLE] "Prometheus executable to read the data with")
+                .default_value("prometheus")))
+        .subcommand(Command::new("grafana")
+            .about("Generate Grafana dashboards and a compose file serving the captured metrics")
+            .arg(arg!(dir: [DIR] "Data directory of the simulation")
+                .value_parser(value_parser!(PathBuf))
+                .default_value("data"))
+            .arg(arg!(output: -o [OUTPUT] "Output directory, defaults to \"grafana\" in the data directory")
+                .value_parser(value_parser!(PathBuf))))
+        .subcommand(Command::new("sweep")
+            .about("Generate a data directory for each variant of the sweep section in the config")
+            .arg(arg!(dir: -d [DIR] "Output directory containing a subdirectory per variant")
+                .value_parser(value_parser!(PathBuf))
+                .default_value("data"))
+            .arg(arg!(config: <CONFIG> "Configuration file with a sweep section. See CONFIG.md")
+                .value_parser(value_parser!(PathBuf))))
+        .subcommand(Command::new("receive-remote-write")
+            .about("Write samples received via Prometheus remote write to a file. Used within simulations")
+            .hide(true)
+            .arg(arg!(port: --port <PORT> "Port to listen on")
+                .value_parser(value_parser!(u16)))
+            .arg(arg!(output: --output <FILE> "File to append the samples to, one JSON object per line")
+                .value_parser(value_parser!(PathBuf))))
         .get_matches();
 
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
 
+    match matches.subcommand() {
+        Some(("schema", matches)) => schema(matches),
+        Some(("sweep", matches)) => sweep(matches),
+        Some(("analyze", matches)) => analyze_logs(matches),
+        Some(("export", matches)) => export_metrics(matches),
+        Some(("grafana", matches)) => grafana(matches),
+        Some(("receive-remote-write", matches)) => receive_remote_write(matches),
+        _ => run(&matches),
+    }
+}
+
+fn run(matches: &ArgMatches) -> Result<()> {
     let dir = matches
         .get_one::<PathBuf>("dir")
         .expect("there is a default in place");
     let config = matches.get_one::<PathBuf>("config").expect("required arg");
 
-    let config = File::open(config).wrap_err("Unable to read the config")?;
-
     let mut invocation =
-        generate(config, dir, false).wrap_err("Failed to generate data directory")?;
+        generate(config.as_path(), dir, false).wrap_err("Failed to generate data directory")?;
 
     if !matches.get_flag("genonly") {
         if let Some(user_args) = matches.get_many::<String>("shadow_cli") {
             invocation.with_user_args(user_args);
         }
-        // if exec() returns, the call failed!
-        Err(invocation.command().exec()).wrap_err("Failed to invoke Shadow")
+        if matches.get_flag("supervise") {
+            let summary = supervise(invocation, dir, std::io::stdout())
+                .wrap_err("Failed to supervise Shadow")?;
+            if !summary.success {
+                bail!(
+                    "Simulation failed, see {} for details",
+                    dir.join(SUMMARY_FILE).display()
+                );
+            }
+            Ok(())
+        } else {
+            // if exec() returns, the call failed!
+            Err(invocation.command().exec()).wrap_err("Failed to invoke Shadow")
+        }
     } else {
         Ok(())
     }
 }
+
+fn schema(matches: &ArgMatches) -> Result<()> {
+    let schema = format!("{:#}", json_schema());
+    match matches.get_one::<PathBuf>("output") {
+        Some(path) => write(path, schema).wrap_err("Unable to write the schema"),
+        None => {
+            println!("{schema}");
+            Ok(())
+        }
+    }
+}
+
+fn sweep(matches: &ArgMatches) -> Result<()> {
+    let dir = matches
+        .get_one::<PathBuf>("dir")
+        .expect("there is a default in place");
+    let config = matches.get_one::<PathBuf>("config").expect("required arg");
+
+    let config = load(config).wrap_err("Failed to read config")?;
+    let variants = generate_sweep(config, dir, false).wrap_err("Failed to generate sweep")?;
+    println!(
+        "Generated {} variants, see {} for details",
+        variants.len(),
+        dir.join(MANIFEST_FILE).display()
+    );
+    Ok(())
+}
+
+fn analyze_logs(matches: &ArgMatches) -> Result<()> {
+    let dir = matches
+        .get_one::<PathBuf>("dir")
+        .expect("there is a default in place");
+    analyze(dir).wrap_err("Failed to analyze simulation")?;
+    println!("Results written to {}", dir.join(ANALYSIS_DIR).display());
+    Ok(())
+}
+
+fn export_metrics(matches: &ArgMatches) -> Result<()> {
+    let dir = matches
+        .get_one::<PathBuf>("dir")
+        .expect("there is a default in place");
+    let options = ExportOptions {
+        queries: matches
+            .get_many::<String>("query")
+            .expect("required arg")
+            .cloned()
+            .collect(),
+        step: Duration::from_secs(*matches.get_one::<u64>("step").expect("default")),
+        output: matches
+            .get_one::<PathBuf>("output")
+            .cloned()
+            .unwrap_or_else(|| dir.join("metrics")),
+        prometheus_executable: matches
+            .get_one::<String>("prometheus")
+            .expect("default")
+            .clone(),
+    };
+    let files = export(dir, &options).wrap_err("Failed to export metrics")?;
+    for file in files {
+        println!("{}", file.display());
+    }
+    Ok(())
+}
+
+fn grafana(matches: &ArgMatches) -> Result<()> {
+    let dir = matches
+        .get_one::<PathBuf>("dir")
+        .expect("there is a default in place");
+    let output = matches
+        .get_one::<PathBuf>("output")
+        .cloned()
+        .unwrap_or_else(|| dir.join(GRAFANA_DIR));
+    generate_grafana(dir, &output).wrap_err("Failed to generate Grafana dashboards")?;
+    println!(
+        "Run \"docker compose -f {} up\" and open http://localhost:3000",
+        output.join(COMPOSE_FILE).display()
+    );
+    Ok(())
+}
+
+fn receive_remote_write(matches: &ArgMatches) -> Result<()> {
+    let port = *matches.get_one::<u16>("port").expect("required arg");
+    let output = matches.get_one::<PathBuf>("output").expect("required arg");
+    receive(port, output).wrap_err("Failed to receive remote write requests")
+}
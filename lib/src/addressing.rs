@@ -0,0 +1,163 @@
+//! Allocation of the IP addresses of the generated hosts.
+
+use crate::config::ethshadow::{IpAllocation, Subnet};
+use crate::Error;
+use rand::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// Number of random picks within a subnet before we search it for free addresses.
+const RANDOM_ATTEMPTS: usize = 64;
+
+/// Hands out unique addresses, following the allocation strategy for hosts without a subnet.
+pub struct IpAllocator {
+    strategy: IpAllocation,
+    used_ips: HashSet<Ipv4Addr>,
+    used_ipv6s: HashSet<Ipv6Addr>,
+    /// Subnets chosen for the strategy, which are never handed out twice
+    used_subnets: HashSet<Subnet>,
+    location_subnets: HashMap<String, Subnet>,
+    /// The subnet currently filled by `shared_subnets`, and the number of hosts in it
+    shared_subnet: Option<(Subnet, usize)>,
+}
+
+impl IpAllocator {
+    pub fn new(strategy: IpAllocation) -> Self {
+        IpAllocator {
+            strategy,
+            used_ips: HashSet::new(),
+            used_ipv6s: HashSet::new(),
+            used_subnets: HashSet::new(),
+            location_subnets: HashMap::new(),
+            shared_subnet: None,
+        }
+    }
+
+    /// Number of IPv4 addresses handed out so far.
+    pub fn allocated(&self) -> usize {
+        self.used_ips.len()
+    }
+
+    /// Allocates an IPv4 address for a host in the given location. If the host's node group has
+    /// a subnet, the address is taken from there.
+    pub fn allocate<R: Rng>(
+        &mut self,
+        location: &str,
+        subnet: Option<Subnet>,
+        rng: &mut R,
+    ) -> Result<Ipv4Addr, Error> {
+        let subnet = match (subnet, self.strategy) {
+            (Some(subnet), _) => Some(subnet),
+            (None, IpAllocation::Random) => None,
+            (None, IpAllocation::SubnetPerLocation { prefix_len }) => {
+                match self.location_subnets.get(location) {
+                    Some(subnet) => Some(*subnet),
+                    None => {
+                        let subnet = self.random_subnet(prefix_len, rng)?;
+                        self.location_subnets.insert(location.to_string(), subnet);
+                        Some(subnet)
+                    }
+                }
+            }
+            (
+                None,
+                IpAllocation::SharedSubnets {
+                    nodes_per_subnet,
+                    prefix_len,
+                },
+            ) => match self.shared_subnet {
+                Some((subnet, hosts)) if hosts < nodes_per_subnet => {
+                    self.shared_subnet = Some((subnet, hosts + 1));
+                    Some(subnet)
+                }
+                _ => {
+                    let subnet = self.random_subnet(prefix_len, rng)?;
+                    self.shared_subnet = Some((subnet, 1));
+                    Some(subnet)
+                }
+            },
+        };
+
+        let Some(subnet) = subnet else {
+            let mut ip = random_ip(rng);
+            while !self.used_ips.insert(ip) {
+                ip = random_ip(rng);
+            }
+            return Ok(ip);
+        };
+        for _ in 0..RANDOM_ATTEMPTS {
+            let ip = subnet.host(rng.gen_range(0..subnet.host_count()));
+            if usable(ip) && self.used_ips.insert(ip) {
+                return Ok(ip);
+            }
+        }
+        // the subnet is small or almost full, so look at every address
+        let free: Vec<Ipv4Addr> = (0..subnet.host_count())
+            .map(|idx| subnet.host(idx))
+            .filter(|ip| usable(*ip) && !self.used_ips.contains(ip))
+            .collect();
+        let ip = *free
+            .choose(rng)
+            .ok_or_else(|| Error::SubnetExhausted(subnet.to_string()))?;
+        self.used_ips.insert(ip);
+        Ok(ip)
+    }
+
+    pub fn allocate_ipv6<R: Rng>(&mut self, rng: &mut R) -> Ipv6Addr {
+        let mut ip = random_ipv6(rng);
+        while !self.used_ipv6s.insert(ip) {
+            ip = random_ipv6(rng);
+        }
+        ip
+    }
+
+    /// Picks a random subnet within the range used by `random_ip` that was not picked before.
+    fn random_subnet<R: Rng>(&mut self, prefix_len: u8, rng: &mut R) -> Result<Subnet, Error> {
+        Subnet::new(Ipv4Addr::UNSPECIFIED, prefix_len).map_err(Error::InvalidSubnet)?;
+        let available = 89u64 << (prefix_len - 8);
+        if self.used_subnets.len() as u64 >= available {
+            return Err(Error::SubnetExhausted(format!("/{prefix_len} subnets")));
+        }
+        loop {
+            let subnet = Subnet::new(random_ip(rng), prefix_len).map_err(Error::InvalidSubnet)?;
+            if self.used_subnets.insert(subnet) {
+                return Ok(subnet);
+            }
+        }
+    }
+}
+
+// we want to avoid hitting a reserved IP range, as that might invoke special behavior in clients.
+// we also want to distribute the addresses as wide as possible, as e.g. `bootnode` has buckets
+// for IP ranges. As there are a lot of reserved IP ranges, we don't bother having each possible
+// IP, and choose 11-99 for the first octet. The last octet will be chosen within 1-254, as the
+// first and last IP of a subnet is special.
+fn random_ip<R: Rng>(rng: &mut R) -> Ipv4Addr {
+    Ipv4Addr::new(
+        rng.gen_range(11..=99),
+        rng.gen(),
+        rng.gen(),
+        rng.gen_range(1..=254),
+    )
+}
+
+// for the same reason, we skip the first and last IP of each /24 within larger subnets.
+fn usable(ip: Ipv4Addr) -> bool {
+    !matches!(ip.octets()[3], 0 | 255)
+}
+
+// similarly, we stay within global unicast space and avoid 2001::/16 and 2002::/16, which contain
+// most special-purpose ranges (Teredo, documentation, 6to4, ...), by choosing 2400-2dff for the
+// first group. We use a random /64 prefix and a random interface identifier.
+fn random_ipv6<R: Rng>(rng: &mut R) -> Ipv6Addr {
+    Ipv6Addr::new(
+        rng.gen_range(0x2400..=0x2dff),
+        rng.gen(),
+        rng.gen(),
+        rng.gen(),
+        rng.gen(),
+        rng.gen(),
+        rng.gen(),
+        rng.gen_range(1..=0xfffe),
+    )
+}
@@ -0,0 +1,196 @@
+//! Parsers for the client log lines we are interested in. Times are given in seconds since the
+//! start of the simulation, which Shadow always starts on 2000-01-01 00:00:00.
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClEvent {
+    /// Periodic status of the beacon node, logged each slot by the slot notifier.
+    Status {
+        time: f64,
+        head_slot: u64,
+        finalized_epoch: Option<u64>,
+        peers: Option<u64>,
+        synced: bool,
+    },
+    /// A block was received via gossip.
+    BlockReceived { time: f64, slot: u64, root: String },
+    /// A block was published by a validator client.
+    BlockPublished { time: f64, slot: u64 },
+    /// An unaggregated attestation of a monitored validator was received via gossip. Only logged
+    /// for validators tracked by Lighthouse's validator monitor.
+    AttestationReceived {
+        time: f64,
+        slot: u64,
+        validator: String,
+    },
+    Reorg {
+        time: f64,
+        previous_slot: u64,
+        new_slot: u64,
+        distance: Option<u64>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElHead {
+    pub time: f64,
+    pub number: u64,
+}
+
+impl ClEvent {
+    pub fn time(&self) -> f64 {
+        match self {
+            ClEvent::Status { time, .. }
+            | ClEvent::BlockReceived { time, .. }
+            | ClEvent::BlockPublished { time, .. }
+            | ClEvent::AttestationReceived { time, .. }
+            | ClEvent::Reorg { time, .. } => *time,
+        }
+    }
+}
+
+/// Parses a line of Lighthouse's (beacon node or validator client) terminal log, e.g.
+/// `Jan 01 00:00:24.000 INFO Synced, slot: 2, epoch: 0, finalized_epoch: 0, peers: 7, ...`
+pub fn parse_lighthouse_line(line: &str) -> Option<ClEvent> {
+    let line = strip_ansi(line);
+    let mut tokens = line.splitn(5, char::is_whitespace);
+    let (month, day, time, level, rest) = (
+        tokens.next()?,
+        tokens.next()?,
+        tokens.next()?,
+        tokens.next()?,
+        tokens.next()?,
+    );
+    if !matches!(level, "INFO" | "WARN" | "ERRO" | "CRIT" | "DEBG") {
+        return None;
+    }
+    let time = lighthouse_time(month, day, time)?;
+    let message = rest.trim_start();
+    let fields = fields(message);
+    let number = |key: &str| fields.get(key).and_then(|value| value.parse::<u64>().ok());
+
+    if message.starts_with("Synced") {
+        Some(ClEvent::Status {
+            time,
+            head_slot: number("slot")?,
+            finalized_epoch: number("finalized_epoch").or_else(|| {
+                // newer versions log the checkpoint as "root:epoch"
+                fields
+                    .get("finalized_checkpoint")
+                    .and_then(|checkpoint| checkpoint.rsplit(':').next()?.parse().ok())
+            }),
+            peers: number("peers"),
+            synced: true,
+        })
+    } else if message.starts_with("Searching for peers") || message.starts_with("Syncing") {
+        Some(ClEvent::Status {
+            time,
+            head_slot: number("head_slot").or_else(|| number("slot"))?,
+            finalized_epoch: number("finalized_epoch"),
+            peers: number("peers"),
+            synced: false,
+        })
+    } else if message.starts_with("New block received") {
+        Some(ClEvent::BlockReceived {
+            time,
+            slot: number("slot")?,
+            root: fields.get("root").copied().unwrap_or_default().to_string(),
+        })
+    } else if message.starts_with("Successfully published block") {
+        Some(ClEvent::BlockPublished {
+            time,
+            slot: number("slot")?,
+        })
+    } else if message.starts_with("Unaggregated attestation")
+        && fields.get("src") == Some(&"gossip")
+    {
+        Some(ClEvent::AttestationReceived {
+            time,
+            slot: number("slot")?,
+            validator: fields.get("validator")?.to_string(),
+        })
+    } else if message.starts_with("Beacon chain re-org") {
+        Some(ClEvent::Reorg {
+            time,
+            previous_slot: number("previous_slot")?,
+            new_slot: number("new_slot")?,
+            distance: number("reorg_distance"),
+        })
+    } else {
+        None
+    }
+}
+
+/// Parses a line of Geth's terminal log, e.g.
+/// `INFO [01-01|00:00:24.123] Chain head was updated  number=2 hash=0x...`
+pub fn parse_geth_line(line: &str) -> Option<ElHead> {
+    let line = strip_ansi(line);
+    let start = line.find('[')?;
+    let end = start + line[start..].find(']')?;
+    let (date, time) = line[start + 1..end].split_once('|')?;
+    let (_, day) = date.split_once('-')?;
+    let time = day_offset(day)? + seconds_of_day(time)?;
+    let message = line[end + 1..].trim_start();
+    if !message.starts_with("Chain head was updated") {
+        return None;
+    }
+    let number = message
+        .split_whitespace()
+        .find_map(|field| field.strip_prefix("number="))?
+        .replace(',', "")
+        .parse()
+        .ok()?;
+    Some(ElHead { time, number })
+}
+
+/// Collects the `key: value` pairs of a Lighthouse log message. Values may be quoted.
+fn fields(message: &str) -> HashMap<&str, &str> {
+    message
+        .split(", ")
+        .filter_map(|part| {
+            let (key, value) = part.split_once(": ")?;
+            let key = key.split_whitespace().last()?;
+            Some((key, value.trim().trim_matches('"')))
+        })
+        .collect()
+}
+
+fn lighthouse_time(month: &str, day: &str, time: &str) -> Option<f64> {
+    // simulations longer than a month are not really feasible, so we only support January
+    if month != "Jan" {
+        return None;
+    }
+    Some(day_offset(day)? + seconds_of_day(time)?)
+}
+
+fn day_offset(day: &str) -> Option<f64> {
+    let day: u64 = day.parse().ok()?;
+    Some((day.checked_sub(1)? * 86400) as f64)
+}
+
+fn seconds_of_day(time: &str) -> Option<f64> {
+    let mut parts = time.splitn(3, ':');
+    let hours: u64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    Some((hours * 3600 + minutes * 60) as f64 + seconds)
+}
+
+fn strip_ansi(line: &str) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // skip until the final byte of the escape sequence
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
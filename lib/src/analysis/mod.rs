@@ -0,0 +1,322 @@
+//! Analysis of a finished simulation, based on the client logs in the data directory.
+//!
+//! We read the output Shadow captured for each process (`shadow/hosts/<host>/*.stdout` and
+//! `*.stderr`) as well as `geth.log` files in the node directories, and recognize the lines by
+//! their format, so it does not matter how the processes are named. Block and attestation
+//! arrivals are joined with the placement of the hosts in the generated network to analyze
+//! propagation.
+
+use crate::analysis::logs::{parse_geth_line, parse_lighthouse_line, ClEvent, ElHead};
+use crate::analysis::propagation::{
+    attestation_arrivals, block_arrivals, propagation_stats, PropagationStats, SlotClock,
+};
+use crate::analysis::topology::read_placements;
+use crate::Error;
+use log::{info, warn};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{create_dir_all, read_dir, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+pub mod logs;
+pub mod propagation;
+pub mod topology;
+
+pub const ANALYSIS_DIR: &str = "analysis";
+
+/// The parsed events of a single host, ordered by time.
+#[derive(Default, Debug)]
+pub struct HostLogs {
+    pub cl_events: Vec<ClEvent>,
+    pub el_heads: Vec<ElHead>,
+}
+
+#[derive(Serialize, Default, Debug)]
+pub struct HostHealth {
+    pub head_slot: Option<u64>,
+    pub finalized_epoch: Option<u64>,
+    pub peers: Option<u64>,
+    pub el_head: Option<u64>,
+    pub reorgs: usize,
+}
+
+#[derive(Serialize, Default, Debug)]
+pub struct ChainHealth {
+    /// The highest slot any node had as head.
+    pub highest_slot: u64,
+    /// Slots up to `highest_slot` for which no node published or received a block.
+    pub missed_slots: Vec<u64>,
+    /// The lowest finalized epoch among all nodes at the end of the simulation.
+    pub finalized_epoch: Option<u64>,
+    pub reorgs: usize,
+    pub max_reorg_distance: Option<u64>,
+    pub hosts: BTreeMap<String, HostHealth>,
+}
+
+#[derive(Debug)]
+pub struct Analysis {
+    pub health: ChainHealth,
+    /// `None` if the data directory lacks the information to determine slot times or placements
+    pub propagation: Option<PropagationStats>,
+    /// Like `propagation`, for the unaggregated attestations of monitored validators
+    pub attestation_propagation: Option<PropagationStats>,
+}
+
+/// Reads the logs of all hosts in the given data directory.
+pub fn read_logs(dir: &Path) -> Result<BTreeMap<String, HostLogs>, Error> {
+    let mut hosts = BTreeMap::new();
+    for entry in read_dir(dir.join("shadow").join("hosts"))? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let name = entry
+            .file_name()
+            .to_str()
+            .ok_or(Error::NonUTF8Path)?
+            .to_string();
+
+        let mut files = vec![];
+        for file in read_dir(entry.path())? {
+            let path = file?.path();
+            if matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("stdout" | "stderr")
+            ) {
+                files.push(path);
+            }
+        }
+        let geth_log = dir.join(&name).join("geth").join("geth.log");
+        if geth_log.is_file() {
+            files.push(geth_log);
+        }
+
+        let mut logs = HostLogs::default();
+        for path in files {
+            // client logs may contain arbitrary bytes, which must not abort the analysis
+            for line in BufReader::new(File::open(&path)?).split(b'\n') {
+                let line = String::from_utf8_lossy(&line?).into_owned();
+                let line = line.trim_end_matches('\r');
+                if let Some(event) = parse_lighthouse_line(line) {
+                    logs.cl_events.push(event);
+                } else if let Some(head) = parse_geth_line(line) {
+                    logs.el_heads.push(head);
+                }
+            }
+        }
+        logs.cl_events.sort_by(|a, b| a.time().total_cmp(&b.time()));
+        logs.el_heads.sort_by(|a, b| a.time.total_cmp(&b.time));
+        // geth might log to both stderr and the log file
+        logs.el_heads.dedup();
+        hosts.insert(name, logs);
+    }
+    Ok(hosts)
+}
+
+pub fn chain_health(hosts: &BTreeMap<String, HostLogs>) -> ChainHealth {
+    let mut health = ChainHealth::default();
+    let mut block_slots = BTreeSet::new();
+    for (name, logs) in hosts {
+        let mut host = HostHealth::default();
+        for event in &logs.cl_events {
+            match event {
+                ClEvent::Status {
+                    head_slot,
+                    finalized_epoch,
+                    peers,
+                    ..
+                } => {
+                    host.head_slot = Some(*head_slot);
+                    host.finalized_epoch = finalized_epoch.or(host.finalized_epoch);
+                    host.peers = peers.or(host.peers);
+                }
+                ClEvent::BlockReceived { slot, .. } | ClEvent::BlockPublished { slot, .. } => {
+                    block_slots.insert(*slot);
+                }
+                ClEvent::Reorg { distance, .. } => {
+                    host.reorgs += 1;
+                    health.max_reorg_distance = health.max_reorg_distance.max(*distance);
+                }
+                ClEvent::AttestationReceived { .. } => {}
+            }
+        }
+        host.el_head = logs.el_heads.last().map(|head| head.number);
+
+        health.highest_slot = health.highest_slot.max(host.head_slot.unwrap_or(0));
+        health.reorgs += host.reorgs;
+        if host.head_slot.is_some() {
+            health.finalized_epoch = Some(match health.finalized_epoch {
+                Some(epoch) => epoch.min(host.finalized_epoch.unwrap_or(0)),
+                None => host.finalized_epoch.unwrap_or(0),
+            });
+        }
+        health.hosts.insert(name.clone(), host);
+    }
+    health.missed_slots = (1..=health.highest_slot)
+        .filter(|slot| !block_slots.contains(slot))
+        .collect();
+    health
+}
+
+/// Analyzes the logs in the given data directory and writes the results into its `analysis`
+/// subdirectory: time series as CSV files, a summary as `summary.json` and block and attestation
+/// propagation statistics as `propagation.json` and `attestation_propagation.json`.
+pub fn analyze(dir: &Path) -> Result<Analysis, Error> {
+    info!("Reading logs");
+    let hosts = read_logs(dir)?;
+    if hosts.values().all(|logs| logs.cl_events.is_empty()) {
+        warn!("No consensus client logs found, has the simulation been run?");
+    }
+    let health = chain_health(&hosts);
+
+    let output = dir.join(ANALYSIS_DIR);
+    create_dir_all(&output)?;
+    info!("Writing results to {}", output.display());
+
+    write_csv(
+        &output.join("head_slots.csv"),
+        "time,host,head_slot,finalized_epoch,peers,synced",
+        hosts.iter().flat_map(|(name, logs)| {
+            logs.cl_events.iter().filter_map(move |event| match event {
+                ClEvent::Status {
+                    time,
+                    head_slot,
+                    finalized_epoch,
+                    peers,
+                    synced,
+                } => Some(format!(
+                    "{time},{name},{head_slot},{},{},{synced}",
+                    optional(finalized_epoch),
+                    optional(peers),
+                )),
+                _ => None,
+            })
+        }),
+    )?;
+    write_csv(
+        &output.join("el_heads.csv"),
+        "time,host,number",
+        hosts.iter().flat_map(|(name, logs)| {
+            logs.el_heads
+                .iter()
+                .map(move |head| format!("{},{name},{}", head.time, head.number))
+        }),
+    )?;
+    write_csv(
+        &output.join("reorgs.csv"),
+        "time,host,previous_slot,new_slot,distance",
+        hosts.iter().flat_map(|(name, logs)| {
+            logs.cl_events.iter().filter_map(move |event| match event {
+                ClEvent::Reorg {
+                    time,
+                    previous_slot,
+                    new_slot,
+                    distance,
+                } => Some(format!(
+                    "{time},{name},{previous_slot},{new_slot},{}",
+                    optional(distance)
+                )),
+                _ => None,
+            })
+        }),
+    )?;
+    serde_json::to_writer_pretty(File::create(output.join("summary.json"))?, &health)?;
+
+    info!(
+        "Highest slot: {}, missed slots: {}, finalized epoch: {}, reorgs: {}",
+        health.highest_slot,
+        health.missed_slots.len(),
+        optional(&health.finalized_epoch),
+        health.reorgs,
+    );
+
+    let (propagation, attestation_propagation) = match (SlotClock::read(dir), read_placements(dir))
+    {
+        (Ok(clock), Ok(placements)) => {
+            let arrivals = block_arrivals(&hosts, &placements, clock);
+            write_csv(
+                &output.join("block_arrivals.csv"),
+                "slot,root,host,location,reliability,time,delay",
+                arrivals.iter().map(|arrival| {
+                    format!(
+                        "{},{},{},{},{},{},{}",
+                        arrival.slot,
+                        arrival.root,
+                        arrival.host,
+                        arrival.location,
+                        arrival.reliability,
+                        arrival.time,
+                        arrival.delay,
+                    )
+                }),
+            )?;
+            let stats = propagation_stats(&arrivals);
+            serde_json::to_writer_pretty(File::create(output.join("propagation.json"))?, &stats)?;
+            if let Some(overall) = &stats.overall {
+                info!(
+                    "Block arrival after slot start: median {:.3}s, p95 {:.3}s, max {:.3}s",
+                    overall.p50, overall.p95, overall.max,
+                );
+            }
+
+            let attestations = attestation_arrivals(&hosts, &placements, clock);
+            write_csv(
+                &output.join("attestation_arrivals.csv"),
+                "slot,validator,host,location,reliability,time,delay",
+                attestations.iter().map(|arrival| {
+                    format!(
+                        "{},{},{},{},{},{},{}",
+                        arrival.slot,
+                        arrival.validator,
+                        arrival.host,
+                        arrival.location,
+                        arrival.reliability,
+                        arrival.time,
+                        arrival.delay,
+                    )
+                }),
+            )?;
+            let attestation_stats = propagation_stats(&attestations);
+            serde_json::to_writer_pretty(
+                File::create(output.join("attestation_propagation.json"))?,
+                &attestation_stats,
+            )?;
+            if let Some(overall) = &attestation_stats.overall {
+                info!(
+                    "Attestation arrival after slot start: median {:.3}s, p95 {:.3}s, max {:.3}s",
+                    overall.p50, overall.p95, overall.max,
+                );
+            }
+            (Some(stats), Some(attestation_stats))
+        }
+        (Err(e), _) | (_, Err(e)) => {
+            warn!("Skipping propagation analysis: {e}");
+            (None, None)
+        }
+    };
+
+    Ok(Analysis {
+        health,
+        propagation,
+        attestation_propagation,
+    })
+}
+
+pub(crate) fn write_csv<I: Iterator<Item = String>>(
+    path: &Path,
+    header: &str,
+    rows: I,
+) -> Result<(), Error> {
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "{header}")?;
+    for row in rows {
+        writeln!(file, "{row}")?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+pub(crate) fn optional<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(T::to_string).unwrap_or_default()
+}
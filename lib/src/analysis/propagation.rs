@@ -0,0 +1,261 @@
+//! Block and attestation propagation: how long after the start of its slot each node received a
+//! block or attestation, broken down by the location and reliability of the receiving node.
+
+use crate::analysis::logs::ClEvent;
+use crate::analysis::topology::Placement;
+use crate::analysis::HostLogs;
+use crate::Error;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::read_to_string;
+use std::path::Path;
+
+const UNKNOWN: &str = "unknown";
+
+/// Converts slots to simulated time, based on the genesis values used for generation.
+#[derive(Debug, Clone, Copy)]
+pub struct SlotClock {
+    /// Simulated time of genesis, in seconds
+    pub genesis: f64,
+    pub seconds_per_slot: f64,
+}
+
+impl SlotClock {
+    /// Reads the genesis time and slot duration from the `values.env` in the data directory.
+    pub fn read(dir: &Path) -> Result<Self, Error> {
+        let values = read_to_string(dir.join("values.env"))?;
+        let values: HashMap<&str, &str> = values
+            .lines()
+            .filter_map(|line| {
+                let (key, value) = line.strip_prefix("export ")?.split_once('=')?;
+                Some((key, value.trim_matches('"')))
+            })
+            .collect();
+        let number = |key: &str| {
+            values
+                .get(key)
+                .and_then(|value| value.parse::<u64>().ok())
+                .ok_or_else(|| Error::MissingGenesisValue(key.to_string()))
+        };
+        let timestamp = number("GENESIS_TIMESTAMP")? + number("GENESIS_DELAY")?;
+        Ok(SlotClock {
+            genesis: timestamp.saturating_sub(crate::genesis::SHADOW_START_TIMESTAMP) as f64,
+            seconds_per_slot: number("SLOT_DURATION_IN_SECONDS")? as f64,
+        })
+    }
+
+    pub fn slot_start(&self, slot: u64) -> f64 {
+        self.genesis + slot as f64 * self.seconds_per_slot
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BlockArrival {
+    pub slot: u64,
+    pub root: String,
+    pub host: String,
+    pub location: String,
+    pub reliability: String,
+    /// Simulated time of arrival, in seconds
+    pub time: f64,
+    /// Seconds since the start of the slot
+    pub delay: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct AttestationArrival {
+    pub slot: u64,
+    /// The validator as logged by the validator monitor, usually its index
+    pub validator: String,
+    pub host: String,
+    pub location: String,
+    pub reliability: String,
+    /// Simulated time of arrival, in seconds
+    pub time: f64,
+    /// Seconds since the start of the slot
+    pub delay: f64,
+}
+
+/// Arrivals which can be grouped by the placement of the receiving host.
+pub trait Arrival {
+    fn location(&self) -> &str;
+    fn reliability(&self) -> &str;
+    fn delay(&self) -> f64;
+}
+
+impl Arrival for BlockArrival {
+    fn location(&self) -> &str {
+        &self.location
+    }
+
+    fn reliability(&self) -> &str {
+        &self.reliability
+    }
+
+    fn delay(&self) -> f64 {
+        self.delay
+    }
+}
+
+impl Arrival for AttestationArrival {
+    fn location(&self) -> &str {
+        &self.location
+    }
+
+    fn reliability(&self) -> &str {
+        &self.reliability
+    }
+
+    fn delay(&self) -> f64 {
+        self.delay
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Percentiles {
+    pub count: usize,
+    pub min: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct PropagationStats {
+    pub overall: Option<Percentiles>,
+    pub by_location: BTreeMap<String, Percentiles>,
+    pub by_reliability: BTreeMap<String, Percentiles>,
+    /// Keyed by `<location>/<reliability>`
+    pub by_location_and_reliability: BTreeMap<String, Percentiles>,
+}
+
+/// Collects the first arrival of each block at each host.
+pub fn block_arrivals(
+    hosts: &BTreeMap<String, HostLogs>,
+    placements: &BTreeMap<String, Placement>,
+    clock: SlotClock,
+) -> Vec<BlockArrival> {
+    let mut arrivals = vec![];
+    for (host, logs) in hosts {
+        let (location, reliability) = placement(placements, host);
+        let mut seen = HashSet::new();
+        for event in &logs.cl_events {
+            let ClEvent::BlockReceived { time, slot, root } = event else {
+                continue;
+            };
+            if !seen.insert((*slot, root)) {
+                continue;
+            }
+            arrivals.push(BlockArrival {
+                slot: *slot,
+                root: root.clone(),
+                host: host.clone(),
+                location: location.to_string(),
+                reliability: reliability.to_string(),
+                time: *time,
+                delay: time - clock.slot_start(*slot),
+            });
+        }
+    }
+    arrivals.sort_by(|a, b| a.slot.cmp(&b.slot).then(a.time.total_cmp(&b.time)));
+    arrivals
+}
+
+/// Collects the first arrival of each monitored validator's attestation at each host.
+pub fn attestation_arrivals(
+    hosts: &BTreeMap<String, HostLogs>,
+    placements: &BTreeMap<String, Placement>,
+    clock: SlotClock,
+) -> Vec<AttestationArrival> {
+    let mut arrivals = vec![];
+    for (host, logs) in hosts {
+        let (location, reliability) = placement(placements, host);
+        let mut seen = HashSet::new();
+        for event in &logs.cl_events {
+            let ClEvent::AttestationReceived {
+                time,
+                slot,
+                validator,
+            } = event
+            else {
+                continue;
+            };
+            if !seen.insert((*slot, validator)) {
+                continue;
+            }
+            arrivals.push(AttestationArrival {
+                slot: *slot,
+                validator: validator.clone(),
+                host: host.clone(),
+                location: location.to_string(),
+                reliability: reliability.to_string(),
+                time: *time,
+                delay: time - clock.slot_start(*slot),
+            });
+        }
+    }
+    arrivals.sort_by(|a, b| a.slot.cmp(&b.slot).then(a.time.total_cmp(&b.time)));
+    arrivals
+}
+
+fn placement<'p>(placements: &'p BTreeMap<String, Placement>, host: &str) -> (&'p str, &'p str) {
+    placements
+        .get(host)
+        .map(|p| (p.location.as_str(), p.reliability.as_str()))
+        .unwrap_or((UNKNOWN, UNKNOWN))
+}
+
+pub fn propagation_stats<A: Arrival>(arrivals: &[A]) -> PropagationStats {
+    let mut by_location: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    let mut by_reliability: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    let mut by_both: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    for arrival in arrivals {
+        by_location
+            .entry(arrival.location().to_string())
+            .or_default()
+            .push(arrival.delay());
+        by_reliability
+            .entry(arrival.reliability().to_string())
+            .or_default()
+            .push(arrival.delay());
+        by_both
+            .entry(format!("{}/{}", arrival.location(), arrival.reliability()))
+            .or_default()
+            .push(arrival.delay());
+    }
+    let all = |groups: BTreeMap<String, Vec<f64>>| {
+        groups
+            .into_iter()
+            .filter_map(|(key, delays)| Some((key, percentiles(delays)?)))
+            .collect()
+    };
+    PropagationStats {
+        overall: percentiles(arrivals.iter().map(Arrival::delay).collect()),
+        by_location: all(by_location),
+        by_reliability: all(by_reliability),
+        by_location_and_reliability: all(by_both),
+    }
+}
+
+/// Computes percentiles using the nearest-rank method.
+pub fn percentiles(mut values: Vec<f64>) -> Option<Percentiles> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    let rank = |p: f64| {
+        let idx = (p / 100.0 * values.len() as f64).ceil() as usize;
+        values[idx.clamp(1, values.len()) - 1]
+    };
+    Some(Percentiles {
+        count: values.len(),
+        min: values[0],
+        p50: rank(50.0),
+        p90: rank(90.0),
+        p95: rank(95.0),
+        p99: rank(99.0),
+        max: values[values.len() - 1],
+    })
+}
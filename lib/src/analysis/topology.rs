@@ -0,0 +1,128 @@
+//! Recovers where each host was placed in the network from a generated data directory.
+//!
+//! If available, we use the placements recorded in `manifest.json`. Otherwise, `shadow.yaml` maps
+//! each host to a network node, whose label in the inline GML graph is `<location>-<reliability>`.
+//! As names may contain dashes themselves, we use the marker files in the node directory (named
+//! after the location and reliability) to split the label.
+
+use crate::manifest::{Manifest, MANIFEST_FILE};
+use crate::Error;
+use serde_yaml::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{read_dir, File};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    pub location: String,
+    pub reliability: String,
+}
+
+/// Returns the placement of each host in the data directory.
+pub fn read_placements(dir: &Path) -> Result<BTreeMap<String, Placement>, Error> {
+    if let Ok(file) = File::open(dir.join(MANIFEST_FILE)) {
+        let manifest: Manifest = serde_json::from_reader(file)?;
+        return Ok(manifest
+            .hosts
+            .into_iter()
+            .map(|host| {
+                let placement = Placement {
+                    location: host.location,
+                    reliability: host.reliability,
+                };
+                (host.name, placement)
+            })
+            .collect());
+    }
+
+    let config: Value = serde_yaml::from_reader(File::open(dir.join("shadow.yaml"))?)?;
+    let gml = config
+        .get("network")
+        .and_then(|network| network.get("graph"))
+        .and_then(|graph| graph.get("inline"))
+        .and_then(Value::as_str)
+        .ok_or_else(|| Error::ExpectedOtherType("network.graph.inline".to_string()))?;
+    let labels = node_labels(gml);
+
+    let mut placements = BTreeMap::new();
+    let Some(hosts) = config.get("hosts").and_then(Value::as_mapping) else {
+        return Ok(placements);
+    };
+    for (name, host) in hosts {
+        let (Some(name), Some(id)) = (
+            name.as_str(),
+            host.get("network_node_id").and_then(Value::as_u64),
+        ) else {
+            continue;
+        };
+        let Some(label) = labels.get(&id) else {
+            continue;
+        };
+        let markers = marker_files(&dir.join(name));
+        if let Some(placement) = split_label(label, &markers) {
+            placements.insert(name.to_string(), placement);
+        }
+    }
+    Ok(placements)
+}
+
+fn node_labels(gml: &str) -> HashMap<u64, String> {
+    let mut labels = HashMap::new();
+    let mut in_node = false;
+    let mut id = None;
+    let mut label = None;
+    for line in gml.lines().map(str::trim) {
+        if line == "node [" {
+            in_node = true;
+        } else if in_node && line == "]" {
+            if let (Some(id), Some(label)) = (id.take(), label.take()) {
+                labels.insert(id, label);
+            }
+            in_node = false;
+        } else if in_node {
+            if let Some(value) = line.strip_prefix("id ") {
+                id = value.trim().parse().ok();
+            } else if let Some(value) = line.strip_prefix("label ") {
+                label = Some(value.trim().trim_matches('"').to_string());
+            }
+        }
+    }
+    labels
+}
+
+fn marker_files(dir: &Path) -> Vec<String> {
+    let Ok(entries) = read_dir(dir) else {
+        return vec![];
+    };
+    entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            if entry.file_type().ok()?.is_file() {
+                entry.file_name().into_string().ok()
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn split_label(label: &str, markers: &[String]) -> Option<Placement> {
+    let candidates = label
+        .match_indices('-')
+        .map(|(idx, _)| (&label[..idx], &label[idx + 1..]));
+    let mut fallback = None;
+    for (location, reliability) in candidates {
+        let placement = Placement {
+            location: location.to_string(),
+            reliability: reliability.to_string(),
+        };
+        if markers.iter().any(|marker| marker == location)
+            && markers.iter().any(|marker| marker == reliability)
+        {
+            return Some(placement);
+        }
+        // hosts defined directly in the Shadow config have no marker files
+        fallback.get_or_insert(placement);
+    }
+    fallback
+}
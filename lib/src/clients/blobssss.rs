@@ -5,10 +5,11 @@ use crate::node::{NodeInfo, SimulationContext};
 use crate::validators::Validator;
 use crate::Error;
 use itertools::Itertools;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use std::collections::HashMap;
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
 pub struct Blobssss {
     #[serde(flatten)]
     pub common: CommonParams,
@@ -41,4 +42,8 @@ impl Client for Blobssss {
             start_time: self.start_time.clone().into(),
         })
     }
+
+    fn client_type(&self) -> &'static str {
+        "blobssss"
+    }
 }
@@ -1,19 +1,24 @@
 use crate::clients::CommonParams;
-use crate::clients::ENGINE_API_PORT;
 use crate::clients::{Client, JSON_RPC_PORT};
+use crate::clients::{EL_PROMETHEUS_PORT, ENGINE_API_PORT};
 use crate::config::shadow::Process;
 use crate::node::{NodeInfo, SimulationContext};
 use crate::utils::log_and_wait;
 use crate::validators::Validator;
 use crate::Error;
+use itertools::Itertools;
 use log::debug;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::fs::{create_dir_all, write};
 use std::process::Command;
 
-const PORT: &str = "21000";
+const PORT: u16 = 21000;
+const NODE_KEY_FILE: &str = "nodekey";
+const STATIC_CONFIG_FILE: &str = "static_peers.toml";
 
-#[derive(Deserialize, Debug, Clone, Default)]
+#[derive(Deserialize, JsonSchema, Debug, Clone, Default)]
 #[serde(default)]
 pub struct Geth {
     #[serde(flatten)]
@@ -22,6 +27,22 @@ pub struct Geth {
 
 #[typetag::deserialize(name = "geth")]
 impl Client for Geth {
+    fn register<'a>(
+        &self,
+        node: &NodeInfo<'a>,
+        ctx: &mut SimulationContext<'a>,
+    ) -> Result<(), Error> {
+        ctx.add_el_http_endpoint(format!("http://{}:{JSON_RPC_PORT}", node.ip()));
+
+        let key = ctx.node_key(node, "el");
+        let dir = node.dir().join("geth");
+        create_dir_all(&dir)?;
+        write(dir.join(NODE_KEY_FILE), key.secret_hex())?;
+        let enode = format!("enode://{}@{}:{PORT}", key.enode_id(), node.ip());
+        ctx.add_el_identity(node, enode.clone(), enode);
+        Ok(())
+    }
+
     fn add_to_node(
         &self,
         node: &NodeInfo,
@@ -48,8 +69,27 @@ impl Client for Geth {
             return Err(Error::ChildProcessFailure("geth init".to_string()));
         }
 
-        ctx.add_el_http_endpoint(format!("http://{}:{JSON_RPC_PORT}", node.ip()));
+        let bootnodes = ctx.el_bootnode_enodes_for(node).join(",");
+        let mut static_args = String::new();
+        if let Some(peering) = ctx.static_peering() {
+            let peers = ctx
+                .el_static_peers_for(node)
+                .iter()
+                .map(|peer| format!("\"{}\"", peer.address))
+                .join(", ");
+            let config_file = format!("{dir}/{STATIC_CONFIG_FILE}");
+            write(
+                &config_file,
+                format!("[Node.P2P]\nStaticNodes = [{peers}]\nTrustedNodes = [{peers}]\n"),
+            )?;
+            static_args = format!("--config {config_file} ");
+            if !peering.discovery {
+                static_args.push_str("--nodiscover ");
+            }
+        }
 
+        // geth listens on all addresses, IPv6 included, but `--nat` takes a single address, so
+        // IPv4 stays the advertised one
         Ok(Process {
             path: executable,
             args: format!(
@@ -61,11 +101,15 @@ impl Client for Geth {
                 --http.port {JSON_RPC_PORT} \
                 --http.api eth,rpc,web3 \
                 --port {PORT} \
+                --nodekey {dir}/{NODE_KEY_FILE} \
                 --bootnodes {} \
                 --nat extip:{} \
-                --log.file {dir}/geth.log {}",
+                --metrics \
+                --metrics.addr 0.0.0.0 \
+                --metrics.port {EL_PROMETHEUS_PORT} \
+                --log.file {dir}/geth.log {static_args}{}",
                 ctx.jwt_path().to_str().ok_or(Error::NonUTF8Path)?,
-                ctx.el_bootnode_enodes().join(","),
+                bootnodes,
                 node.ip(),
                 self.common.arguments("--syncmode full --ipcdisable"),
             ),
@@ -75,6 +119,23 @@ impl Client for Geth {
         })
     }
 
+    fn client_type(&self) -> &'static str {
+        "geth"
+    }
+
+    fn ports(&self) -> Vec<(&'static str, u16)> {
+        vec![
+            ("p2p", PORT),
+            ("engine_api", ENGINE_API_PORT),
+            ("json_rpc", JSON_RPC_PORT),
+            ("metrics", EL_PROMETHEUS_PORT),
+        ]
+    }
+
+    fn metrics_endpoint(&self) -> Option<(u16, &'static str)> {
+        Some((EL_PROMETHEUS_PORT, "/debug/metrics/prometheus"))
+    }
+
     fn is_el_client(&self) -> bool {
         true
     }
@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use std::fs::{create_dir, File};
 use std::io::Write;
 
-use libsecp256k1::{PublicKey, SecretKey};
+use schemars::JsonSchema;
 use serde::Deserialize;
 
 use crate::clients::{Client, Validator};
@@ -12,8 +12,9 @@ use crate::node::{NodeInfo, SimulationContext};
 use crate::Error;
 
 const DISC_PORT: u16 = 30305;
+const KEY_FILE: &str = "boot.key";
 
-#[derive(Deserialize, Debug, Clone, Default)]
+#[derive(Deserialize, JsonSchema, Debug, Clone, Default)]
 #[serde(default)]
 pub struct GethBootnode {
     #[serde(flatten)]
@@ -22,30 +23,36 @@ pub struct GethBootnode {
 
 #[typetag::deserialize(name = "geth_bootnode")]
 impl Client for GethBootnode {
-    fn add_to_node(
+    fn register<'a>(
         &self,
-        node: &NodeInfo,
-        ctx: &mut SimulationContext,
-        _validators: &[Validator],
-    ) -> Result<Process, Error> {
+        node: &NodeInfo<'a>,
+        ctx: &mut SimulationContext<'a>,
+    ) -> Result<(), Error> {
         let dir = node.dir().join("geth_bootnode");
         create_dir(&dir)?;
 
-        let node_key = SecretKey::random(ctx.rng());
-        let pub_key = PublicKey::from_secret_key(&node_key);
+        let key = ctx.node_key(node, "el_bootnode");
+        let mut file = File::create_new(dir.join(KEY_FILE))?;
+        file.write_all(key.secret_hex().as_bytes())?;
 
-        let node_key = hex::encode(node_key.serialize());
-        let pub_key = hex::encode(&pub_key.serialize()[1..]);
+        let ip = node.ip();
+        ctx.add_el_bootnode_enode(
+            format!("enode://{}@{ip}:0?discport={DISC_PORT}", key.enode_id()),
+            node.location(),
+        );
+        Ok(())
+    }
 
-        let key_file = dir.join("boot.key");
-        let mut file = File::create_new(&key_file)?;
-        file.write_all(node_key.as_bytes())?;
+    fn add_to_node(
+        &self,
+        node: &NodeInfo,
+        _ctx: &mut SimulationContext,
+        _validators: &[Validator],
+    ) -> Result<Process, Error> {
+        let key_file = node.dir().join("geth_bootnode").join(KEY_FILE);
         let key_file = key_file.to_str().ok_or(Error::NonUTF8Path)?;
-
         let ip = node.ip();
 
-        ctx.add_el_bootnode_enode(format!("enode://{pub_key}@{ip}:0?discport={DISC_PORT}"));
-
         Ok(Process {
             path: self.common.executable_or("bootnode"),
             args: format!(
@@ -59,4 +66,12 @@ impl Client for GethBootnode {
             start_time: "0s".into(),
         })
     }
+
+    fn client_type(&self) -> &'static str {
+        "geth_bootnode"
+    }
+
+    fn ports(&self) -> Vec<(&'static str, u16)> {
+        vec![("discovery", DISC_PORT)]
+    }
 }
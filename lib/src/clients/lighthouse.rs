@@ -5,12 +5,15 @@ use crate::config::shadow::Process;
 use crate::node::{NodeInfo, SimulationContext};
 use crate::validators::Validator;
 use crate::Error;
+use itertools::Itertools;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::fs::{create_dir_all, write};
 
-const PORT: &str = "31000";
+const PORT: u16 = 31000;
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
 #[serde(default)]
 pub struct Lighthouse {
     #[serde(flatten)]
@@ -29,6 +32,24 @@ impl Default for Lighthouse {
 
 #[typetag::deserialize(name = "lighthouse")]
 impl Client for Lighthouse {
+    fn register<'a>(
+        &self,
+        node: &NodeInfo<'a>,
+        ctx: &mut SimulationContext<'a>,
+    ) -> Result<(), Error> {
+        ctx.add_cl_http_endpoint(format!("{}:{BEACON_API_PORT}", node.ip()));
+
+        // Lighthouse picks up the raw secret key from its network directory
+        let key = ctx.node_key(node, "cl");
+        let dir = node.dir().join("lighthouse/beacon/network");
+        create_dir_all(&dir)?;
+        write(dir.join("key"), key.secret_bytes())?;
+        let peer_id = key.peer_id();
+        let address = format!("/ip4/{}/tcp/{PORT}/p2p/{peer_id}", node.ip());
+        ctx.add_cl_identity(node, address, peer_id);
+        Ok(())
+    }
+
     fn add_to_node<'a>(
         &self,
         node: &NodeInfo<'a>,
@@ -40,13 +61,7 @@ impl Client for Lighthouse {
 
         let ip = node.ip();
 
-        ctx.add_cl_http_endpoint(format!("{ip}:{BEACON_API_PORT}"));
-        ctx.add_cl_monitoring_endpoint(
-            node.location(),
-            node.reliability(),
-            format!("{ip}:{CL_PROMETHEUS_PORT}"),
-        );
-
+        let bootnodes = ctx.cl_bootnode_enrs_for(node).join(",");
         let mut args = format!(
             "--testnet-dir \"{}\" \
                 beacon_node \
@@ -65,10 +80,24 @@ impl Client for Lighthouse {
                 --metrics {}",
             ctx.metadata_path().to_str().ok_or(Error::NonUTF8Path)?,
             ctx.jwt_path().to_str().ok_or(Error::NonUTF8Path)?,
-            ctx.cl_bootnode_enrs().join(","),
+            bootnodes,
             self.common
                 .arguments("--disable-quic --disable-upnp --disable-packet-filter"),
         );
+        args.push_str(&dual_stack_args(node, PORT, true));
+        if let Some(peering) = ctx.static_peering() {
+            let peers = ctx.cl_static_peers_for(node);
+            if !peers.is_empty() {
+                args.push_str(&format!(
+                    " --libp2p-addresses {} --trusted-peers {} ",
+                    peers.iter().map(|peer| peer.address.as_str()).join(","),
+                    peers.iter().map(|peer| peer.id.as_str()).join(","),
+                ));
+            }
+            if !peering.discovery {
+                args.push_str(" --disable-discovery ");
+            }
+        }
         if self.lower_target_peers && ctx.num_cl_clients() <= 100 {
             args.push_str(&format!("--target-peers {}", ctx.num_cl_clients() - 1));
         }
@@ -82,7 +111,38 @@ impl Client for Lighthouse {
         })
     }
 
+    fn client_type(&self) -> &'static str {
+        "lighthouse"
+    }
+
+    fn ports(&self) -> Vec<(&'static str, u16)> {
+        vec![
+            ("p2p", PORT),
+            ("beacon_api", BEACON_API_PORT),
+            ("metrics", CL_PROMETHEUS_PORT),
+        ]
+    }
+
+    fn metrics_endpoint(&self) -> Option<(u16, &'static str)> {
+        Some((CL_PROMETHEUS_PORT, "/metrics"))
+    }
+
     fn is_cl_client(&self) -> bool {
         true
     }
 }
+
+/// With dual stack addressing, lets Lighthouse listen on both addresses. Beacon nodes are also
+/// told to advertise the IPv6 address, while boot nodes take it from their ENR file.
+pub(crate) fn dual_stack_args(node: &NodeInfo, port: u16, advertise: bool) -> String {
+    let Some(ipv6) = node.ipv6() else {
+        return String::new();
+    };
+    let mut args = format!(" --listen-address 0.0.0.0 --listen-address :: --port6 {port} ");
+    if advertise {
+        args.push_str(&format!(
+            "--enr-address {ipv6} --enr-udp6-port {port} --enr-tcp6-port {port} "
+        ));
+    }
+    args
+}
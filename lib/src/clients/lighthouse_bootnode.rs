@@ -1,38 +1,46 @@
+use crate::clients::lighthouse::dual_stack_args;
 use crate::clients::CommonParams;
-use log::debug;
+use enr::k256::ecdsa::SigningKey;
+use enr::Enr;
+use log::warn;
+use schemars::JsonSchema;
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
-use std::fs::read_to_string;
-use std::process::Command;
+use std::fs::{create_dir_all, write};
+use std::path::Path;
 
 use crate::clients::{Client, Validator};
 use crate::config::shadow::Process;
 use crate::node::{NodeInfo, SimulationContext};
-use crate::utils::log_and_wait;
+use crate::CowStr;
 use crate::Error;
-use crate::{genesis, CowStr};
 
-const PORT: &str = "4011";
+const PORT: u16 = 4011;
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, JsonSchema, Debug, Clone, Default)]
 #[serde(default)]
 pub struct LighthouseBootnode {
     #[serde(flatten)]
     pub common: CommonParams,
-    pub lcli_executable: CowStr,
-}
-
-impl Default for LighthouseBootnode {
-    fn default() -> Self {
-        Self {
-            common: CommonParams::default(),
-            lcli_executable: "lcli".into(),
-        }
-    }
+    /// Deprecated and ignored, the ENR is generated by ethshadow
+    pub lcli_executable: Option<CowStr>,
 }
 
 #[typetag::deserialize(name = "lighthouse_bootnode")]
 impl Client for LighthouseBootnode {
+    fn register<'a>(
+        &self,
+        node: &NodeInfo<'a>,
+        ctx: &mut SimulationContext<'a>,
+    ) -> Result<(), Error> {
+        warn_lcli_executable(self.lcli_executable.as_deref());
+        let dir = node.dir().join("lighthouse_bootnode");
+        let enr = generate_enr(node, ctx, "cl_bootnode", PORT, &dir)?;
+        ctx.add_cl_bootnode_enr(enr, node.location());
+        Ok(())
+    }
+
     fn add_to_node(
         &self,
         node: &NodeInfo,
@@ -40,32 +48,6 @@ impl Client for LighthouseBootnode {
         _validators: &[Validator],
     ) -> Result<Process, Error> {
         let dir = node.dir().join("lighthouse_bootnode");
-        debug!("Calling lcli generate-bootnode-enr");
-        let status = log_and_wait(
-            Command::new(self.lcli_executable.as_ref())
-                .arg("generate-bootnode-enr")
-                .arg("--testnet-dir")
-                .arg(ctx.metadata_path())
-                .arg("--ip")
-                .arg(node.ip().to_string())
-                .arg("--udp-port")
-                .arg(PORT)
-                .arg("--tcp-port")
-                .arg(PORT)
-                .arg("--genesis-fork-version")
-                .arg(genesis::GENESIS_FORK_VERSION)
-                .arg("--output-dir")
-                .arg(&dir),
-        )?;
-        if !status.success() {
-            return Err(Error::ChildProcessFailure(
-                "lcli generate-bootnode-enr".to_string(),
-            ));
-        }
-
-        let enr_path = dir.join("enr.dat");
-        let enr = read_to_string(enr_path)?;
-        ctx.add_cl_bootnode_enr(enr);
 
         Ok(Process {
             path: self.common.executable_or("lighthouse"),
@@ -73,14 +55,80 @@ impl Client for LighthouseBootnode {
                 "--testnet-dir \"{}\" \
                 boot_node \
                 --port {PORT} \
-                --network-dir {} {}",
+                --network-dir {} {}{}",
                 ctx.metadata_path().to_str().ok_or(Error::NonUTF8Path)?,
                 dir.to_str().ok_or(Error::NonUTF8Path)?,
                 self.common.arguments("--disable-packet-filter"),
+                dual_stack_args(node, PORT, false),
             ),
             environment: HashMap::new(),
             expected_final_state: "running".into(),
             start_time: "0s".into(),
         })
     }
+
+    fn client_type(&self) -> &'static str {
+        "lighthouse_bootnode"
+    }
+
+    fn ports(&self) -> Vec<(&'static str, u16)> {
+        vec![("discovery", PORT)]
+    }
+}
+
+/// Warns that `lcli_executable` is no longer used, if it is set.
+pub(crate) fn warn_lcli_executable(lcli_executable: Option<&str>) {
+    if let Some(lcli_executable) = lcli_executable {
+        warn!(
+            "Ignoring lcli_executable {lcli_executable}, the ENR is generated by ethshadow and \
+            lcli is no longer needed"
+        );
+    }
+}
+
+/// Writes the key of the node and its ENR to `dir`, where `lighthouse boot_node` picks them up,
+/// and returns the ENR. Like `lcli generate-bootnode-enr`, the `eth2` entry is derived from the
+/// genesis fork version only. The enr crate uses randomized signatures, so only the key and node
+/// id of the record are reproducible with the same seed.
+pub(crate) fn generate_enr(
+    node: &NodeInfo,
+    ctx: &SimulationContext,
+    kind: &str,
+    port: u16,
+    dir: &Path,
+) -> Result<String, Error> {
+    let key = ctx.node_key(node, kind);
+    let signing_key =
+        SigningKey::from_slice(&key.secret_bytes()).expect("node keys are valid secp256k1 keys");
+    let mut builder = Enr::builder();
+    builder.ip4(node.ip()).tcp4(port).udp4(port).add_value(
+        "eth2",
+        &enr_fork_id(ctx.genesis().genesis_fork_version())?.to_vec(),
+    );
+    if let Some(ipv6) = node.ipv6() {
+        builder.ip6(ipv6).tcp6(port).udp6(port);
+    }
+    let enr = builder.build(&signing_key)?.to_base64();
+    create_dir_all(dir)?;
+    write(dir.join("key"), key.secret_bytes())?;
+    write(dir.join("enr.dat"), &enr)?;
+    Ok(enr)
+}
+
+/// The SSZ encoded `ENRForkID` before genesis: the fork digest with a zero genesis validators
+/// root, and no scheduled fork.
+fn enr_fork_id(genesis_fork_version: &str) -> Result<[u8; 16], Error> {
+    let version: [u8; 4] = hex::decode(genesis_fork_version.trim_start_matches("0x"))
+        .ok()
+        .and_then(|version| version.try_into().ok())
+        .ok_or_else(|| Error::InvalidForkVersion(genesis_fork_version.to_string()))?;
+    // hash tree root of the fork data: the version padded to a chunk, and the root
+    let mut fork_data = [0; 64];
+    fork_data[..4].copy_from_slice(&version);
+    let digest = Sha256::digest(fork_data);
+
+    let mut fork_id = [0xff; 16];
+    fork_id[..4].copy_from_slice(&digest[..4]);
+    fork_id[4..8].copy_from_slice(&version);
+    Ok(fork_id)
 }
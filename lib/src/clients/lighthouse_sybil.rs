@@ -0,0 +1,174 @@
+use crate::clients::lighthouse::dual_stack_args;
+use crate::clients::lighthouse_bootnode::{generate_enr, warn_lcli_executable};
+use crate::clients::Client;
+use crate::clients::CommonParams;
+use crate::clients::ENGINE_API_PORT;
+use crate::config::shadow::Process;
+use crate::node::{NodeInfo, PeerIdentity, SimulationContext, SybilEnr};
+use crate::validators::Validator;
+use crate::CowStr;
+use crate::Error;
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::{create_dir_all, write};
+
+const PORT: u16 = 34000;
+
+/// A lightweight attacker node which tries to occupy the peer slots of the nodes with the
+/// `victims` tag, for eclipse attack experiments.
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub struct LighthouseSybil {
+    #[serde(flatten)]
+    pub common: CommonParams,
+    /// Tag of the attacked nodes
+    pub victims: String,
+    pub mode: SybilMode,
+    /// With `boot_node`, the victims receive only the sybils as bootnodes
+    pub exclusive: bool,
+    /// With `beacon_node`, the number of peers the sybil tries to keep
+    pub target_peers: usize,
+    /// Deprecated and ignored, the ENR is generated by ethshadow
+    pub lcli_executable: Option<CowStr>,
+}
+
+#[derive(Deserialize, JsonSchema, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SybilMode {
+    /// A discovery-only `lighthouse boot_node`, handed to the victims as bootnode
+    #[default]
+    BootNode,
+    /// A `lighthouse beacon_node` without execution client, dialing the victims directly
+    BeaconNode,
+}
+
+impl Default for LighthouseSybil {
+    fn default() -> Self {
+        Self {
+            common: CommonParams::default(),
+            victims: "victim".to_string(),
+            mode: SybilMode::BootNode,
+            exclusive: true,
+            target_peers: 500,
+            lcli_executable: None,
+        }
+    }
+}
+
+impl LighthouseSybil {
+    fn victims<'c, 'a>(&self, ctx: &'c SimulationContext<'a>) -> Vec<&'c PeerIdentity<'a>> {
+        ctx.cl_identities()
+            .iter()
+            .filter(|identity| identity.tag == Some(self.victims.as_str()))
+            .collect()
+    }
+}
+
+#[typetag::deserialize(name = "lighthouse_sybil")]
+impl Client for LighthouseSybil {
+    fn register<'a>(
+        &self,
+        node: &NodeInfo<'a>,
+        ctx: &mut SimulationContext<'a>,
+    ) -> Result<(), Error> {
+        warn_lcli_executable(self.lcli_executable.as_deref());
+        let dir = node.dir().join("lighthouse_sybil");
+        match self.mode {
+            SybilMode::BootNode => {
+                let enr = generate_enr(node, ctx, "sybil", PORT, &dir)?;
+                ctx.add_sybil_enr(SybilEnr {
+                    victims: self.victims.clone(),
+                    enr,
+                    exclusive: self.exclusive,
+                });
+            }
+            SybilMode::BeaconNode => {
+                let key = ctx.node_key(node, "sybil");
+                let dir = dir.join("beacon/network");
+                create_dir_all(&dir)?;
+                write(dir.join("key"), key.secret_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn add_to_node<'a>(
+        &self,
+        node: &NodeInfo<'a>,
+        ctx: &mut SimulationContext<'a>,
+        _validators: &[Validator],
+    ) -> Result<Process, Error> {
+        let victims = self.victims(ctx);
+        if victims.is_empty() {
+            return Err(Error::NoVictims(self.victims.clone()));
+        }
+        let dir = node.dir().join("lighthouse_sybil");
+        let dir = dir.to_str().ok_or(Error::NonUTF8Path)?;
+        let metadata_path = ctx.metadata_path().to_str().ok_or(Error::NonUTF8Path)?;
+
+        let (args, start_time) = match self.mode {
+            SybilMode::BootNode => (
+                format!(
+                    "--testnet-dir \"{metadata_path}\" \
+                    boot_node \
+                    --port {PORT} \
+                    --network-dir {dir} {}{}",
+                    self.common.arguments("--disable-packet-filter"),
+                    dual_stack_args(node, PORT, false),
+                ),
+                "0s",
+            ),
+            // Lighthouse requires an execution endpoint, but nothing listens on the sybil host, so
+            // the sybil cannot import blocks, but keeps its peers busy
+            SybilMode::BeaconNode => (
+                format!(
+                    "--testnet-dir \"{metadata_path}\" \
+                    beacon_node \
+                    --datadir \"{dir}\" \
+                    --execution-endpoint http://localhost:{ENGINE_API_PORT} \
+                    --execution-jwt \"{}\" \
+                    --port {PORT} \
+                    --enr-address {ip} \
+                    --enr-udp-port {PORT} \
+                    --enr-tcp-port {PORT} \
+                    --libp2p-addresses {} \
+                    --trusted-peers {} \
+                    --target-peers {} {}{}",
+                    ctx.jwt_path().to_str().ok_or(Error::NonUTF8Path)?,
+                    victims
+                        .iter()
+                        .map(|victim| victim.address.as_str())
+                        .join(","),
+                    victims.iter().map(|victim| victim.id.as_str()).join(","),
+                    self.target_peers,
+                    self.common
+                        .arguments("--disable-quic --disable-upnp --disable-packet-filter"),
+                    dual_stack_args(node, PORT, true),
+                    ip = node.ip(),
+                ),
+                "5s",
+            ),
+        };
+
+        Ok(Process {
+            path: self.common.executable_or("lighthouse"),
+            args,
+            environment: HashMap::new(),
+            expected_final_state: "running".into(),
+            start_time: start_time.into(),
+        })
+    }
+
+    fn client_type(&self) -> &'static str {
+        "lighthouse_sybil"
+    }
+
+    fn ports(&self) -> Vec<(&'static str, u16)> {
+        match self.mode {
+            SybilMode::BootNode => vec![("discovery", PORT)],
+            SybilMode::BeaconNode => vec![("p2p", PORT)],
+        }
+    }
+}
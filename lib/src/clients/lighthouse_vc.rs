@@ -1,16 +1,17 @@
 use crate::clients::CommonParams;
-use crate::clients::BEACON_API_PORT;
 use crate::clients::{Client, ValidatorDemand};
+use crate::clients::{BEACON_API_PORT, VC_PROMETHEUS_PORT};
 use crate::config::shadow::Process;
 use crate::node::{NodeInfo, SimulationContext};
 use crate::validators::Validator;
 use crate::Error;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::fs;
 use std::fs::create_dir;
 
-#[derive(Deserialize, Debug, Clone, Default)]
+#[derive(Deserialize, JsonSchema, Debug, Clone, Default)]
 #[serde(default)]
 pub struct LighthouseValidatorClient {
     #[serde(flatten)]
@@ -60,6 +61,9 @@ impl Client for LighthouseValidatorClient {
                 validator_client \
                 --datadir \"{dir_str}\" \
                 --beacon-nodes http://localhost:{BEACON_API_PORT} \
+                --metrics \
+                --metrics-address 0.0.0.0 \
+                --metrics-port {VC_PROMETHEUS_PORT} \
                 --init-slashing-protection {}",
                 ctx.metadata_path().to_str().ok_or(Error::NonUTF8Path)?,
                 self.common.arguments(
@@ -72,6 +76,18 @@ impl Client for LighthouseValidatorClient {
         })
     }
 
+    fn client_type(&self) -> &'static str {
+        "lighthouse_vc"
+    }
+
+    fn ports(&self) -> Vec<(&'static str, u16)> {
+        vec![("metrics", VC_PROMETHEUS_PORT)]
+    }
+
+    fn metrics_endpoint(&self) -> Option<(u16, &'static str)> {
+        Some((VC_PROMETHEUS_PORT, "/metrics"))
+    }
+
     fn validator_demand(&self) -> ValidatorDemand {
         match self.validators {
             None => ValidatorDemand::Any,
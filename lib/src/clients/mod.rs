@@ -1,23 +1,41 @@
+use crate::clients::blobssss::Blobssss;
+use crate::clients::geth::Geth;
+use crate::clients::geth_bootnode::GethBootnode;
+use crate::clients::lighthouse::Lighthouse;
+use crate::clients::lighthouse_bootnode::LighthouseBootnode;
+use crate::clients::lighthouse_sybil::LighthouseSybil;
+use crate::clients::lighthouse_vc::LighthouseValidatorClient;
+use crate::clients::prometheus::Prometheus;
+use crate::clients::remote_write_file::RemoteWriteFile;
+use crate::clients::reth::Reth;
 use crate::config::shadow::Process;
 use crate::node::{NodeInfo, SimulationContext};
 use crate::validators::Validator;
 use crate::CowStr;
 use crate::Error;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, ObjectValidation, Schema, SchemaObject, SubschemaValidation};
+use schemars::JsonSchema;
 use serde::Deserialize;
 use std::fmt::Debug;
 
-const ENGINE_API_PORT: &str = "21001";
-const JSON_RPC_PORT: &str = "22001";
-const BEACON_API_PORT: &str = "31001";
-const CL_PROMETHEUS_PORT: &str = "32001";
+const ENGINE_API_PORT: u16 = 21001;
+const JSON_RPC_PORT: u16 = 22001;
+const BEACON_API_PORT: u16 = 31001;
+const CL_PROMETHEUS_PORT: u16 = 32001;
+const EL_PROMETHEUS_PORT: u16 = 23001;
+const VC_PROMETHEUS_PORT: u16 = 33001;
+const REMOTE_WRITE_PORT: u16 = 9201;
 
 pub mod blobssss;
 pub mod geth;
 pub mod geth_bootnode;
 pub mod lighthouse;
 pub mod lighthouse_bootnode;
+pub mod lighthouse_sybil;
 pub mod lighthouse_vc;
 pub mod prometheus;
+pub mod remote_write_file;
 pub mod reth;
 
 pub enum ValidatorDemand {
@@ -33,6 +51,17 @@ pub enum ValidatorDemand {
 
 #[typetag::deserialize(tag = "type")]
 pub trait Client: Debug {
+    /// Called for the clients of all nodes before any of them is added, so that clients can make
+    /// themselves known to others, e.g. by registering endpoints or bootnode identities. This way,
+    /// every client sees the whole network in `add_to_node`, regardless of the order of the nodes.
+    fn register<'a>(
+        &self,
+        _node: &NodeInfo<'a>,
+        _ctx: &mut SimulationContext<'a>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
     fn add_to_node<'a>(
         &self,
         node: &NodeInfo<'a>,
@@ -40,6 +69,19 @@ pub trait Client: Debug {
         validators: &[Validator],
     ) -> Result<Process, Error>;
 
+    /// The name of this client type, as used for `type` in the config.
+    fn client_type(&self) -> &'static str;
+
+    /// The ports this client listens on, by purpose.
+    fn ports(&self) -> Vec<(&'static str, u16)> {
+        vec![]
+    }
+
+    /// The port and path at which this client serves Prometheus metrics, if any.
+    fn metrics_endpoint(&self) -> Option<(u16, &'static str)> {
+        None
+    }
+
     fn validator_demand(&self) -> ValidatorDemand {
         ValidatorDemand::None
     }
@@ -52,7 +94,69 @@ pub trait Client: Debug {
     }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+/// Stand-in for `Box<dyn Client>` when generating the JSON schema: a union of all client types,
+/// distinguished by the `type` property. Keep this in sync with the `typetag` names above.
+pub struct AnyClient;
+
+impl JsonSchema for AnyClient {
+    fn schema_name() -> String {
+        "Client".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let variants = vec![
+            tagged::<Blobssss>(gen, "blobssss"),
+            tagged::<Geth>(gen, "geth"),
+            tagged::<GethBootnode>(gen, "geth_bootnode"),
+            tagged::<Lighthouse>(gen, "lighthouse"),
+            tagged::<LighthouseBootnode>(gen, "lighthouse_bootnode"),
+            tagged::<LighthouseSybil>(gen, "lighthouse_sybil"),
+            tagged::<LighthouseValidatorClient>(gen, "lighthouse_vc"),
+            tagged::<Prometheus>(gen, "prometheus"),
+            tagged::<RemoteWriteFile>(gen, "remote_write_file"),
+            tagged::<Reth>(gen, "reth"),
+        ];
+        SchemaObject {
+            subschemas: Some(Box::new(SubschemaValidation {
+                one_of: Some(variants),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+fn tagged<C: JsonSchema>(gen: &mut SchemaGenerator, name: &str) -> Schema {
+    let tag = SchemaObject {
+        instance_type: Some(InstanceType::Object.into()),
+        object: Some(Box::new(ObjectValidation {
+            properties: [(
+                "type".to_string(),
+                SchemaObject {
+                    const_value: Some(name.into()),
+                    ..Default::default()
+                }
+                .into(),
+            )]
+            .into_iter()
+            .collect(),
+            required: ["type".to_string()].into_iter().collect(),
+            ..Default::default()
+        })),
+        ..Default::default()
+    };
+    SchemaObject {
+        subschemas: Some(Box::new(SubschemaValidation {
+            all_of: Some(vec![gen.subschema_for::<C>(), tag.into()]),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
 #[serde(default)]
 pub struct CommonParams {
     pub executable: String,
@@ -1,80 +1,347 @@
-use crate::clients::Client;
 use crate::clients::CommonParams;
+use crate::clients::{Client, REMOTE_WRITE_PORT};
 use crate::config::shadow::Process;
-use crate::node::{NodeInfo, SimulationContext};
+use crate::node::{MonitoringEndpoint, NodeInfo, SimulationContext};
+use crate::utils::stable_hash;
 use crate::validators::Validator;
 use crate::Error;
+use humantime_serde::re::humantime::parse_duration;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use serde_yaml::to_writer;
-use std::collections::HashMap;
-use std::fs::File;
+use serde_yaml::{to_writer, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{copy, create_dir_all, File};
+use std::path::PathBuf;
+use std::time::Duration;
 
-#[derive(Deserialize, Debug, Clone, Default)]
+const HTTP_PORT: u16 = 9090;
+
+/// Prometheus' default scrape timeout, which must not exceed the scrape interval
+const DEFAULT_SCRAPE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
 #[serde(default)]
 pub struct Prometheus {
     #[serde(flatten)]
     common: CommonParams,
+    /// How often to scrape the clients, in Prometheus' duration format, e.g. "1s"
+    pub scrape_interval: String,
+    /// How often to evaluate the rules, in Prometheus' duration format
+    pub evaluation_interval: Option<String>,
+    /// How long to retain the data, in Prometheus' duration format
+    pub retention: Option<String>,
+    /// Additional jobs scraping arbitrary targets
+    pub extra_targets: Vec<ExtraTargets>,
+    /// Recording and alerting rule files, relative to the config file. They are copied into the
+    /// node directory, so their file names must be unique.
+    pub rule_files: Vec<PathBuf>,
+    /// Remote write endpoints to send all scraped samples to
+    pub remote_write: Vec<RemoteWrite>,
+    /// Send all scraped samples to a `remote_write_file` client on the same node
+    pub remote_write_to_file: bool,
+    /// Only scrape a subset of the clients
+    pub shard: Option<Shard>,
+    /// Instead of scraping the clients, federate all series from the sharded instances
+    pub aggregate: bool,
+}
+
+/// Selects the hosts scraped by a Prometheus instance. All clients of a host are in the same shard.
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+#[serde(tag = "by", rename_all = "snake_case")]
+pub enum Shard {
+    /// Hosts in one of the given locations
+    Location { locations: Vec<String> },
+    /// Hosts with one of the given tags
+    Tag { tags: Vec<String> },
+    /// Hosts whose name hashes to `index` modulo `count`
+    Hash { count: u64, index: u64 },
+}
+
+impl Shard {
+    fn includes(&self, endpoint: &MonitoringEndpoint) -> bool {
+        match self {
+            Shard::Location { locations } => locations.contains(&endpoint.location),
+            Shard::Tag { tags } => endpoint.tag.as_ref().is_some_and(|tag| tags.contains(tag)),
+            Shard::Hash { count, index } => stable_hash(&endpoint.host) % count == *index,
+        }
+    }
+}
+
+impl Default for Prometheus {
+    fn default() -> Self {
+        Self {
+            common: CommonParams::default(),
+            scrape_interval: "15s".to_string(),
+            evaluation_interval: None,
+            retention: None,
+            extra_targets: vec![],
+            rule_files: vec![],
+            remote_write: vec![],
+            remote_write_to_file: false,
+            shard: None,
+            aggregate: false,
+        }
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+pub struct ExtraTargets {
+    pub job_name: String,
+    /// `host:port` of each target
+    pub targets: Vec<String>,
+    #[serde(default = "default_metrics_path")]
+    pub metrics_path: String,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct RemoteWrite {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// In Prometheus' duration format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_timeout: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// Any other remote write setting, passed to Prometheus as is
+    #[serde(flatten)]
+    #[schemars(with = "BTreeMap<String, serde_json::Value>")]
+    pub other: BTreeMap<String, Value>,
+}
+
+fn default_metrics_path() -> String {
+    "/metrics".to_string()
 }
 
 #[derive(Serialize)]
 struct PrometheusYaml<'a> {
+    global: Global<'a>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    rule_files: Vec<String>,
     scrape_configs: Vec<ScrapeConfig<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    remote_write: Vec<RemoteWrite>,
+}
+
+#[derive(Serialize)]
+struct Global<'a> {
+    scrape_interval: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scrape_timeout: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    evaluation_interval: Option<&'a str>,
 }
 
 #[derive(Serialize)]
 struct ScrapeConfig<'a> {
-    job_name: String,
-    scrape_interval: String,
+    job_name: &'a str,
+    metrics_path: &'a str,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    honor_labels: bool,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    params: BTreeMap<&'a str, Vec<&'a str>>,
     static_configs: Vec<StaticConfig<'a>>,
 }
 
 #[derive(Serialize)]
 struct StaticConfig<'a> {
-    targets: &'a [String],
-    labels: HashMap<&'a str, &'a str>,
+    targets: Vec<&'a str>,
+    labels: BTreeMap<&'a str, &'a str>,
+}
+
+impl Prometheus {
+    /// Prometheus rejects a scrape timeout larger than the scrape interval, so we have to lower
+    /// the timeout for short intervals.
+    fn scrape_timeout(&self) -> Result<Option<&str>, Error> {
+        let interval = parse_duration(&self.scrape_interval).map_err(|e| {
+            Error::InvalidTime("prometheus scrape_interval".to_string(), e.to_string())
+        })?;
+        Ok((interval < DEFAULT_SCRAPE_TIMEOUT).then_some(self.scrape_interval.as_str()))
+    }
+
+    /// One job per client type, as they might serve metrics at different paths, plus the extra
+    /// targets.
+    fn client_jobs<'a>(&'a self, ctx: &'a SimulationContext) -> Vec<ScrapeConfig<'a>> {
+        let mut jobs: BTreeMap<&str, ScrapeConfig> = BTreeMap::new();
+        let endpoints = ctx.monitoring_endpoints().iter().filter(|endpoint| {
+            self.shard
+                .as_ref()
+                .map_or(true, |shard| shard.includes(endpoint))
+        });
+        for endpoint in endpoints {
+            let mut labels: BTreeMap<&str, &str> = [
+                ("client_type", endpoint.client_type),
+                ("client_id", endpoint.client_id.as_str()),
+                ("host", endpoint.host.as_str()),
+                ("location", endpoint.location.as_str()),
+                ("reliability", endpoint.reliability.as_str()),
+            ]
+            .into_iter()
+            .collect();
+            if let Some(tag) = &endpoint.tag {
+                labels.insert("tag", tag);
+            }
+            jobs.entry(endpoint.client_type)
+                .or_insert_with(|| ScrapeConfig {
+                    job_name: endpoint.client_type,
+                    metrics_path: endpoint.metrics_path,
+                    honor_labels: false,
+                    params: BTreeMap::new(),
+                    static_configs: vec![],
+                })
+                .static_configs
+                .push(StaticConfig {
+                    targets: vec![endpoint.target.as_str()],
+                    labels,
+                });
+        }
+        let mut scrape_configs: Vec<_> = jobs.into_values().collect();
+        for extra in &self.extra_targets {
+            scrape_configs.push(ScrapeConfig {
+                job_name: &extra.job_name,
+                metrics_path: &extra.metrics_path,
+                honor_labels: false,
+                params: BTreeMap::new(),
+                static_configs: vec![StaticConfig {
+                    targets: extra.targets.iter().map(String::as_str).collect(),
+                    labels: extra
+                        .labels
+                        .iter()
+                        .map(|(key, value)| (key.as_str(), value.as_str()))
+                        .collect(),
+                }],
+            });
+        }
+        scrape_configs
+    }
+
+    /// Pulls all series from the sharded instances, keeping their original labels.
+    fn federation_jobs<'a>(
+        &'a self,
+        ctx: &'a SimulationContext,
+    ) -> Result<Vec<ScrapeConfig<'a>>, Error> {
+        if ctx.prometheus_shards().is_empty() {
+            return Err(Error::NoPrometheusShards);
+        }
+        Ok(vec![ScrapeConfig {
+            job_name: "federate",
+            metrics_path: "/federate",
+            honor_labels: true,
+            params: BTreeMap::from([("match[]", vec![r#"{__name__=~".+"}"#])]),
+            static_configs: vec![StaticConfig {
+                targets: ctx.prometheus_shards().iter().map(String::as_str).collect(),
+                labels: BTreeMap::new(),
+            }],
+        }])
+    }
 }
 
 #[typetag::deserialize(name = "prometheus")]
 impl Client for Prometheus {
+    fn register(&self, node: &NodeInfo, ctx: &mut SimulationContext) -> Result<(), Error> {
+        if self.shard.is_some() {
+            ctx.add_prometheus_shard(format!("{}:{HTTP_PORT}", node.ip()));
+        }
+        Ok(())
+    }
+
     fn add_to_node(
         &self,
         node: &NodeInfo,
         ctx: &mut SimulationContext,
         _validators: &[Validator],
     ) -> Result<Process, Error> {
+        if let Some(Shard::Hash { count, index }) = self.shard {
+            if index >= count {
+                return Err(Error::InvalidShard(format!(
+                    "index {index} is not below count {count}"
+                )));
+            }
+        }
         let dir = node.dir().join("prometheus");
         let config_file = node.dir().join("prometheus.yaml");
 
+        let scrape_configs = if self.aggregate {
+            self.federation_jobs(ctx)?
+        } else {
+            self.client_jobs(ctx)
+        };
+
+        let rule_files = if self.rule_files.is_empty() {
+            vec![]
+        } else {
+            let rules_dir = node.dir().join("prometheus_rules");
+            create_dir_all(&rules_dir)?;
+            let mut names = HashSet::new();
+            self.rule_files
+                .iter()
+                .map(|file| {
+                    let name = file
+                        .file_name()
+                        .ok_or_else(|| Error::InvalidRuleFile(file.display().to_string()))?;
+                    // the files are copied into a single directory
+                    if !names.insert(name) {
+                        return Err(Error::DuplicateRuleFile(
+                            name.to_string_lossy().into_owned(),
+                        ));
+                    }
+                    let dest = rules_dir.join(name);
+                    copy(file, &dest)?;
+                    Ok(dest.to_str().ok_or(Error::NonUTF8Path)?.to_string())
+                })
+                .collect::<Result<_, Error>>()?
+        };
+
+        let mut remote_write = self.remote_write.clone();
+        if self.remote_write_to_file {
+            remote_write.push(RemoteWrite {
+                url: format!("http://localhost:{REMOTE_WRITE_PORT}/write"),
+                name: Some("file".to_string()),
+                remote_timeout: None,
+                headers: BTreeMap::new(),
+                other: BTreeMap::new(),
+            });
+        }
+
         let config = PrometheusYaml {
-            scrape_configs: vec![ScrapeConfig {
-                job_name: "lighthouses".to_string(),
-                scrape_interval: "15s".to_string(),
-                static_configs: ctx
-                    .cl_monitoring_endpoints()
-                    .iter()
-                    .map(|((location, reliability), targets)| StaticConfig {
-                        targets,
-                        labels: [("location", *location), ("reliability", *reliability)]
-                            .into_iter()
-                            .collect(),
-                    })
-                    .collect(),
-            }],
+            global: Global {
+                scrape_interval: &self.scrape_interval,
+                scrape_timeout: self.scrape_timeout()?,
+                evaluation_interval: self.evaluation_interval.as_deref(),
+            },
+            rule_files,
+            scrape_configs,
+            remote_write,
         };
 
         to_writer(File::create_new(&config_file)?, &config)?;
 
+        let mut args = format!(
+            "--storage.tsdb.path={} --config.file={} ",
+            dir.to_str().ok_or(Error::NonUTF8Path)?,
+            config_file.to_str().ok_or(Error::NonUTF8Path)?,
+        );
+        if let Some(retention) = &self.retention {
+            args.push_str(&format!("--storage.tsdb.retention.time={retention} "));
+        }
+        args.push_str(&self.common.arguments(""));
+
         Ok(Process {
             path: self.common.executable_or("prometheus"),
-            args: format!(
-                "--storage.tsdb.path={} --config.file={} {}",
-                dir.to_str().ok_or(Error::NonUTF8Path)?,
-                config_file.to_str().ok_or(Error::NonUTF8Path)?,
-                self.common.arguments(""),
-            ),
+            args,
             environment: HashMap::default(),
             expected_final_state: "running".into(),
             start_time: "10s".into(),
         })
     }
+
+    fn client_type(&self) -> &'static str {
+        "prometheus"
+    }
+
+    fn ports(&self) -> Vec<(&'static str, u16)> {
+        vec![("http", HTTP_PORT)]
+    }
 }
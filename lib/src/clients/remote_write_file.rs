@@ -0,0 +1,62 @@
+use crate::clients::CommonParams;
+use crate::clients::{Client, REMOTE_WRITE_PORT};
+use crate::config::shadow::Process;
+use crate::node::{NodeInfo, SimulationContext};
+use crate::validators::Validator;
+use crate::Error;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env::current_exe;
+
+pub const REMOTE_WRITE_FILE: &str = "remote_write.jsonl";
+
+/// Receives the samples of the Prometheus instances with `remote_write_to_file` on the same node,
+/// and appends them to `remote_write.jsonl` in the node directory. Runs `ethshadow` itself.
+#[derive(Deserialize, JsonSchema, Debug, Clone, Default)]
+#[serde(default)]
+pub struct RemoteWriteFile {
+    #[serde(flatten)]
+    pub common: CommonParams,
+}
+
+#[typetag::deserialize(name = "remote_write_file")]
+impl Client for RemoteWriteFile {
+    fn add_to_node(
+        &self,
+        node: &NodeInfo,
+        _ctx: &mut SimulationContext,
+        _validators: &[Validator],
+    ) -> Result<Process, Error> {
+        let path = if self.common.executable.is_empty() {
+            current_exe()?
+                .to_str()
+                .ok_or(Error::NonUTF8Path)?
+                .to_string()
+                .into()
+        } else {
+            self.common.executable.clone().into()
+        };
+        let output = node.dir().join(REMOTE_WRITE_FILE);
+
+        Ok(Process {
+            path,
+            args: format!(
+                "receive-remote-write --port {REMOTE_WRITE_PORT} --output \"{}\" {}",
+                output.to_str().ok_or(Error::NonUTF8Path)?,
+                self.common.arguments(""),
+            ),
+            environment: HashMap::new(),
+            expected_final_state: "running".into(),
+            start_time: "0s".into(),
+        })
+    }
+
+    fn client_type(&self) -> &'static str {
+        "remote_write_file"
+    }
+
+    fn ports(&self) -> Vec<(&'static str, u16)> {
+        vec![("remote_write", REMOTE_WRITE_PORT)]
+    }
+}
@@ -1,16 +1,20 @@
 use crate::clients::CommonParams;
-use crate::clients::ENGINE_API_PORT;
 use crate::clients::{Client, JSON_RPC_PORT};
+use crate::clients::{EL_PROMETHEUS_PORT, ENGINE_API_PORT};
 use crate::config::shadow::Process;
 use crate::node::{NodeInfo, SimulationContext};
 use crate::validators::Validator;
 use crate::Error;
+use itertools::Itertools;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::fs::{create_dir_all, write};
 
-const PORT: &str = "21000";
+const PORT: u16 = 21000;
+const SECRET_KEY_FILE: &str = "p2p-secret-key";
 
-#[derive(Deserialize, Debug, Clone, Default)]
+#[derive(Deserialize, JsonSchema, Debug, Clone, Default)]
 #[serde(default)]
 pub struct Reth {
     #[serde(flatten)]
@@ -19,6 +23,22 @@ pub struct Reth {
 
 #[typetag::deserialize(name = "reth")]
 impl Client for Reth {
+    fn register<'a>(
+        &self,
+        node: &NodeInfo<'a>,
+        ctx: &mut SimulationContext<'a>,
+    ) -> Result<(), Error> {
+        ctx.add_el_http_endpoint(format!("http://{}:{JSON_RPC_PORT}", node.ip()));
+
+        let key = ctx.node_key(node, "el");
+        let dir = node.dir().join("reth");
+        create_dir_all(&dir)?;
+        write(dir.join(SECRET_KEY_FILE), key.secret_hex())?;
+        let enode = format!("enode://{}@{}:{PORT}", key.enode_id(), node.ip());
+        ctx.add_el_identity(node, enode.clone(), enode);
+        Ok(())
+    }
+
     fn add_to_node(
         &self,
         node: &NodeInfo,
@@ -31,7 +51,27 @@ impl Client for Reth {
         let dir = node.dir().join("reth");
         let dir = dir.to_str().ok_or(Error::NonUTF8Path)?;
 
-        ctx.add_el_http_endpoint(format!("http://{}:{JSON_RPC_PORT}", node.ip()));
+        let bootnodes = ctx.el_bootnode_enodes_for(node).join(",");
+        let mut static_args = String::new();
+        if let Some(peering) = ctx.static_peering() {
+            let peers = ctx.el_static_peers_for(node);
+            if !peers.is_empty() {
+                static_args.push_str(&format!(
+                    "--trusted-peers {} ",
+                    peers.iter().map(|peer| peer.address.as_str()).join(","),
+                ));
+            }
+            if !peering.discovery {
+                static_args.push_str("--disable-discovery ");
+            }
+        }
+
+        // `--nat` takes a single address, so IPv4 stays the advertised one for RLPx and discv4,
+        // while discv5 gets its own IPv6 socket
+        let ipv6_args = match node.ipv6() {
+            Some(ipv6) => format!("--addr :: --discovery.addr :: --discovery.v5.addr.ipv6 {ipv6} "),
+            None => String::new(),
+        };
 
         Ok(Process {
             path: self.common.executable_or("reth"),
@@ -46,11 +86,13 @@ impl Client for Reth {
                 --http.port {JSON_RPC_PORT} \
                 --http.api eth,rpc,web3 \
                 --port {PORT} \
+                --p2p-secret-key {dir}/{SECRET_KEY_FILE} \
                 --bootnodes {} \
                 --nat extip:{} \
-                --log.file.directory {dir} {}",
+                --metrics 0.0.0.0:{EL_PROMETHEUS_PORT} \
+                --log.file.directory {dir} {ipv6_args}{static_args}{}",
                 ctx.jwt_path().to_str().ok_or(Error::NonUTF8Path)?,
-                ctx.el_bootnode_enodes().join(","),
+                bootnodes,
                 node.ip(),
                 self.common.arguments("--ipcdisable"),
             ),
@@ -60,6 +102,23 @@ impl Client for Reth {
         })
     }
 
+    fn client_type(&self) -> &'static str {
+        "reth"
+    }
+
+    fn ports(&self) -> Vec<(&'static str, u16)> {
+        vec![
+            ("p2p", PORT),
+            ("engine_api", ENGINE_API_PORT),
+            ("json_rpc", JSON_RPC_PORT),
+            ("metrics", EL_PROMETHEUS_PORT),
+        ]
+    }
+
+    fn metrics_endpoint(&self) -> Option<(u16, &'static str)> {
+        Some((EL_PROMETHEUS_PORT, "/metrics"))
+    }
+
     fn is_el_client(&self) -> bool {
         true
     }
@@ -0,0 +1,187 @@
+//! Composition of config files: `include`s of other files, YAML merge keys (`<<: *anchor`) and
+//! substitution of environment variables (`${VAR}` or `${VAR:-default}`).
+
+use crate::error::Error;
+use serde_yaml::{Mapping, Value};
+use std::env;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// Reads a config file and resolves its includes. Paths in `include` and other file paths in the
+/// config are relative to the file they are mentioned in.
+pub fn load(path: &Path) -> Result<Value, Error> {
+    load_included(path, &mut vec![])
+}
+
+fn load_included(path: &Path, stack: &mut Vec<PathBuf>) -> Result<Value, Error> {
+    let canonical = path
+        .canonicalize()
+        .map_err(|e| Error::Include(path.display().to_string(), e))?;
+    if stack.contains(&canonical) {
+        return Err(Error::IncludeCycle(path.display().to_string()));
+    }
+    let file = File::open(&canonical).map_err(|e| Error::Include(path.display().to_string(), e))?;
+    let mut value: Value = serde_yaml::from_reader(file)?;
+    let base_dir = canonical
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    stack.push(canonical);
+    resolve_includes(&mut value, &base_dir, stack)?;
+    stack.pop();
+    Ok(value)
+}
+
+/// Applies merge keys and replaces the `include` key of the root mapping with the contents of the
+/// mentioned files. Later includes override earlier ones, and the including file overrides all
+/// included files.
+pub fn resolve_includes(
+    value: &mut Value,
+    base_dir: &Path,
+    stack: &mut Vec<PathBuf>,
+) -> Result<(), Error> {
+    value.apply_merge()?;
+    resolve_paths(value, base_dir);
+    let Value::Mapping(mapping) = value else {
+        return Ok(());
+    };
+    let includes = match mapping.remove("include") {
+        None | Some(Value::Null) => return Ok(()),
+        Some(Value::String(include)) => vec![include],
+        Some(Value::Sequence(includes)) => includes
+            .into_iter()
+            .map(|include| match include {
+                Value::String(include) => Ok(include),
+                _ => Err(Error::ExpectedOtherType("include".to_string())),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => return Err(Error::ExpectedOtherType("include".to_string())),
+    };
+
+    let mut merged = Value::Mapping(Mapping::new());
+    for include in includes {
+        let included = load_included(&base_dir.join(include), stack)?;
+        merge(&mut merged, included);
+    }
+    merge(&mut merged, Value::Mapping(std::mem::take(mapping)));
+    *value = merged;
+    Ok(())
+}
+
+/// Makes the file paths in the config relative to `base_dir`, so that they point to the same files
+/// regardless of the working directory. Paths starting with an environment variable are left as
+/// they are.
+fn resolve_paths(value: &mut Value, base_dir: &Path) {
+    let Some(ethereum) = value.get_mut("ethereum") else {
+        return;
+    };
+    if let Some(alloc_files) = ethereum
+        .get_mut("genesis")
+        .and_then(|genesis| genesis.get_mut("alloc_files"))
+    {
+        resolve_path_list(alloc_files, base_dir);
+    }
+    if let Some(Value::Mapping(clients)) = ethereum.get_mut("clients") {
+        for (_, client) in clients {
+            if let Some(rule_files) = client.get_mut("rule_files") {
+                resolve_path_list(rule_files, base_dir);
+            }
+        }
+    }
+}
+
+fn resolve_path_list(paths: &mut Value, base_dir: &Path) {
+    let Value::Sequence(paths) = paths else {
+        return;
+    };
+    for path in paths {
+        if let Value::String(path) = path {
+            if !path.starts_with('$') && Path::new(path).is_relative() {
+                *path = base_dir.join(&path).to_string_lossy().into_owned();
+            }
+        }
+    }
+}
+
+/// Deeply merges `other` into `base`. Mappings are merged key by key, all other values (including
+/// sequences) are replaced.
+fn merge(base: &mut Value, other: Value) {
+    match (base, other) {
+        (Value::Mapping(base), Value::Mapping(other)) => {
+            for (key, value) in other {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, other) => *base = other,
+    }
+}
+
+/// Replaces references to environment variables in all strings. If a string consists of a single
+/// reference only, the substituted value is parsed as YAML scalar, so that e.g. numbers can be
+/// passed via environment variables. Use `$$` for a literal `$`.
+pub fn substitute_env_vars(value: &mut Value) -> Result<(), Error> {
+    match value {
+        Value::String(s) if s.contains('$') => {
+            let whole_reference = s.starts_with("${") && s.find('}') == Some(s.len() - 1);
+            let substituted = substitute(s)?;
+            *value = if whole_reference {
+                match serde_yaml::from_str(&substituted) {
+                    Ok(scalar @ (Value::Bool(_) | Value::Number(_) | Value::String(_))) => scalar,
+                    _ => Value::String(substituted),
+                }
+            } else {
+                Value::String(substituted)
+            };
+        }
+        Value::Sequence(seq) => {
+            for value in seq {
+                substitute_env_vars(value)?;
+            }
+        }
+        Value::Mapping(mapping) => {
+            for (_, value) in mapping {
+                substitute_env_vars(value)?;
+            }
+        }
+        Value::Tagged(tagged) => substitute_env_vars(&mut tagged.value)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn substitute(s: &str) -> Result<String, Error> {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(idx) = rest.find('$') {
+        result.push_str(&rest[..idx]);
+        rest = &rest[idx + 1..];
+        if let Some(tail) = rest.strip_prefix('$') {
+            result.push('$');
+            rest = tail;
+        } else if let Some(tail) = rest.strip_prefix('{') {
+            let end = tail
+                .find('}')
+                .ok_or_else(|| Error::InvalidEnvVarReference(s.to_string()))?;
+            let reference = &tail[..end];
+            let (name, default) = match reference.split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (reference, None),
+            };
+            match (env::var(name), default) {
+                (Ok(value), _) => result.push_str(&value),
+                (Err(_), Some(default)) => result.push_str(default),
+                (Err(_), None) => return Err(Error::MissingEnvVar(name.to_string())),
+            }
+            rest = &tail[end + 1..];
+        } else {
+            result.push('$');
+        }
+    }
+    result.push_str(rest);
+    Ok(result)
+}
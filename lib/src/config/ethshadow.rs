@@ -4,40 +4,61 @@ use crate::clients::lighthouse::Lighthouse;
 use crate::clients::lighthouse_bootnode::LighthouseBootnode;
 use crate::clients::lighthouse_vc::LighthouseValidatorClient;
 use crate::clients::prometheus::Prometheus;
+use crate::clients::remote_write_file::RemoteWriteFile;
 use crate::clients::reth::Reth;
-use crate::clients::Client;
+use crate::clients::{AnyClient, Client};
 use crate::config::one_or_many::OneOrMany;
 use crate::error::Error;
+use crate::genesis::SHADOW_START_TIMESTAMP;
 use crate::CowStr;
+use humantime_serde::re::humantime::parse_duration;
 use humantime_serde::Serde as HumanReadable;
 use itertools::Itertools;
-use serde::{Deserialize, Deserializer};
-use std::collections::HashMap;
-use std::fmt::Debug;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Schema, SchemaObject};
+use schemars::JsonSchema;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_yaml::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Display, Formatter};
+use std::net::Ipv4Addr;
+use std::num::NonZeroU64;
+use std::path::PathBuf;
+use std::str::FromStr;
 use std::time::Duration;
 
 /// Options contained in the configuration file.
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, JsonSchema, Debug, Default)]
 #[serde(default)]
 pub struct EthShadowConfig {
     #[serde(deserialize_with = "deserialize_nodes")]
+    #[schemars(with = "NodeConfig")]
     nodes: Vec<SugaredNode>,
     pub locations: HashMap<CowStr, Location>,
     pub reliabilities: HashMap<CowStr, Reliability>,
     pub validators: Option<usize>,
+    #[schemars(with = "HashMap<String, AnyClient>")]
     pub clients: HashMap<CowStr, Box<dyn Client>>,
     #[serde(default = "default_clients")]
     pub default_clients: HashMap<CowStr, CowStr>,
     pub genesis: Genesis,
     pub topology: Topology,
+    pub bootnodes: Bootnodes,
+    /// Connect the clients according to a precomputed peer graph instead of relying on discovery
+    pub static_peering: Option<StaticPeering>,
+    pub addressing: Addressing,
     pub shadow_path: Option<String>,
+    /// Allow a runahead larger than the smallest latency in the network graph. This may cause
+    /// Shadow to deliver packets later than it should.
+    pub allow_large_runahead: bool,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, JsonSchema, Clone, Debug)]
 #[serde(untagged)]
 enum NodeConfig {
     Simple(usize),
-    Detailed(Vec<SugaredNode>),
+    Detailed(#[schemars(with = "Vec<SugaredNodeSchema>")] Vec<SugaredNode>),
 }
 
 impl Default for NodeConfig {
@@ -55,6 +76,8 @@ fn deserialize_nodes<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<SugaredNode>
                 clients: default_boot_clients(),
                 count: NodeCount::TotalCount(1),
                 tag: Some("boot".into()),
+                subnet: None,
+                simple_boot_hosts: true,
             },
             SugaredNode {
                 locations: OneOrMany::One("europe".into()),
@@ -62,6 +85,8 @@ fn deserialize_nodes<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<SugaredNode>
                 clients: HashMap::new(),
                 count: NodeCount::TotalCount(count),
                 tag: None,
+                subnet: None,
+                simple_boot_hosts: false,
             },
         ],
         NodeConfig::Detailed(vec) => vec,
@@ -80,6 +105,31 @@ struct SugaredNode {
     pub count: NodeCount,
     #[serde(default)]
     pub tag: Option<String>,
+    #[serde(default)]
+    pub subnet: Option<Subnet>,
+    /// Placeholder for the boot hosts of the simple form, which are placed according to the
+    /// `bootnodes` section.
+    #[serde(skip)]
+    simple_boot_hosts: bool,
+}
+
+/// Stand-in for [`SugaredNode`] when generating the JSON schema, as schemars does not support
+/// aliases.
+#[derive(JsonSchema)]
+#[schemars(rename = "Node")]
+#[allow(dead_code)]
+struct SugaredNodeSchema {
+    location: Option<OneOrMany<String>>,
+    locations: Option<OneOrMany<String>>,
+    reliability: Option<OneOrMany<String>>,
+    reliabilities: Option<OneOrMany<String>>,
+    #[serde(default)]
+    clients: HashMap<String, OneOrMany<String>>,
+    count: Option<NodeCount>,
+    tag: Option<String>,
+    /// Allocate the IPs of these hosts from the given subnet, e.g. `44.12.0.0/16`
+    #[schemars(with = "Option<String>")]
+    subnet: Option<Subnet>,
 }
 
 impl SugaredNode {
@@ -103,7 +153,7 @@ impl SugaredNode {
     }
 }
 
-#[derive(Deserialize, Clone, Copy, Debug)]
+#[derive(Deserialize, JsonSchema, Clone, Copy, Debug)]
 enum NodeCount {
     #[serde(rename = "per_combination")]
     CountPerCombination(usize),
@@ -117,21 +167,245 @@ impl Default for NodeCount {
     }
 }
 
-#[derive(Deserialize, Default, Clone, Debug)]
+/// Placement of the boot hosts and selection of the bootnodes passed to each client.
+#[derive(Deserialize, JsonSchema, Clone, Debug)]
+#[serde(default)]
+pub struct Bootnodes {
+    /// Number of boot hosts if `nodes` is a number
+    pub count: usize,
+    /// Locations to spread the boot hosts over if `nodes` is a number
+    pub locations: OneOrMany<String>,
+    pub strategy: BootnodeStrategy,
+    /// Number of bootnodes each client receives with the `random` strategy
+    pub per_client: usize,
+}
+
+impl Default for Bootnodes {
+    fn default() -> Self {
+        Bootnodes {
+            count: 1,
+            locations: OneOrMany::One("europe".into()),
+            strategy: BootnodeStrategy::All,
+            per_client: 2,
+        }
+    }
+}
+
+impl Bootnodes {
+    /// Spreads the boot hosts evenly over the locations, in order.
+    fn count_per_location(&self) -> impl Iterator<Item = (&str, usize)> {
+        let locations = self.locations.len();
+        (&self.locations)
+            .into_iter()
+            .enumerate()
+            .map(move |(idx, location)| {
+                let extra = usize::from(idx < self.count % locations);
+                (location.as_str(), self.count / locations + extra)
+            })
+            .filter(|(_, count)| *count > 0)
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BootnodeStrategy {
+    /// Every client receives all bootnodes
+    #[default]
+    All,
+    /// Clients receive the bootnodes in the location with the lowest latency to their own
+    NearestLocation,
+    /// Clients receive `per_client` randomly chosen bootnodes
+    Random,
+}
+
+#[derive(Deserialize, JsonSchema, Clone, Debug)]
+#[serde(default)]
+pub struct StaticPeering {
+    pub topology: PeerTopology,
+    /// Number of peers per client. Limited by the number of clients.
+    pub degree: usize,
+    /// Probability of replacing a peer by a random one with the `small_world` topology, between
+    /// 0 and 1
+    #[serde(deserialize_with = "deserialize_probability")]
+    #[schemars(range(min = 0, max = 1))]
+    pub rewire_probability: f64,
+    /// Keep discovery enabled in addition to the static peers
+    pub discovery: bool,
+}
+
+impl Default for StaticPeering {
+    fn default() -> Self {
+        StaticPeering {
+            topology: PeerTopology::RandomRegular,
+            degree: 8,
+            rewire_probability: 0.1,
+            discovery: false,
+        }
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PeerTopology {
+    /// Each client is connected to `degree` random clients, as far as possible
+    #[default]
+    RandomRegular,
+    /// A ring where each client is connected to its `degree` nearest neighbours, with some
+    /// connections rewired randomly (Watts-Strogatz)
+    SmallWorld,
+    /// Clients are connected to random clients in their own location, and to one client in
+    /// another location
+    Location,
+}
+
+/// Addresses assigned to the generated hosts.
+#[derive(Deserialize, JsonSchema, Default, Clone, Debug)]
+#[serde(default)]
+pub struct Addressing {
+    pub ip_version: IpVersion,
+    pub allocation: IpAllocation,
+}
+
+#[derive(Deserialize, JsonSchema, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IpVersion {
+    /// Each host has an IPv4 address (the default)
+    #[default]
+    Ipv4,
+    /// Each host has an IPv4 and an IPv6 address
+    DualStack,
+    /// Each host has an IPv6 address only. Not supported by Shadow yet.
+    Ipv6,
+}
+
+/// How IPv4 addresses are assigned to hosts without an explicit `subnet`.
+#[derive(Deserialize, JsonSchema, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum IpAllocation {
+    /// Each host receives a random address (the default)
+    #[default]
+    Random,
+    /// Each location receives a random subnet, e.g. a /16, and its hosts random addresses in it
+    SubnetPerLocation {
+        #[serde(default = "default_location_prefix_len")]
+        prefix_len: u8,
+    },
+    /// Hosts are packed into random subnets, e.g. /24s, `nodes_per_subnet` at a time, emulating
+    /// hosting providers
+    SharedSubnets {
+        nodes_per_subnet: usize,
+        #[serde(default = "default_shared_prefix_len")]
+        prefix_len: u8,
+    },
+}
+
+fn default_location_prefix_len() -> u8 {
+    16
+}
+
+fn default_shared_prefix_len() -> u8 {
+    24
+}
+
+/// An IPv4 subnet in CIDR notation, e.g. `44.12.0.0/16`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Subnet {
+    network: Ipv4Addr,
+    prefix_len: u8,
+}
+
+impl Subnet {
+    /// Subnets larger than a /8 would span the reserved ranges.
+    pub const MIN_PREFIX_LEN: u8 = 8;
+    /// Subnets smaller than a /30 have no room for hosts besides the network and broadcast address.
+    pub const MAX_PREFIX_LEN: u8 = 30;
+
+    pub fn new(address: Ipv4Addr, prefix_len: u8) -> Result<Self, String> {
+        if prefix_len < Self::MIN_PREFIX_LEN {
+            return Err(format!(
+                "prefix length {prefix_len} is too short, use at least {}",
+                Self::MIN_PREFIX_LEN
+            ));
+        }
+        if prefix_len > Self::MAX_PREFIX_LEN {
+            return Err(format!(
+                "prefix length {prefix_len} leaves no room for hosts, use at most {}",
+                Self::MAX_PREFIX_LEN
+            ));
+        }
+        let mask = u32::MAX << (32 - prefix_len);
+        Ok(Subnet {
+            network: Ipv4Addr::from(u32::from(address) & mask),
+            prefix_len,
+        })
+    }
+
+    pub fn network(&self) -> Ipv4Addr {
+        self.network
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Number of addresses usable for hosts, i.e. without the network and broadcast address.
+    pub fn host_count(&self) -> u32 {
+        (1 << (32 - self.prefix_len)) - 2
+    }
+
+    /// The `idx`th host address, starting at 0.
+    pub fn host(&self, idx: u32) -> Ipv4Addr {
+        Ipv4Addr::from(u32::from(self.network) + 1 + idx)
+    }
+}
+
+impl Display for Subnet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+impl FromStr for Subnet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, prefix_len) = s
+            .trim()
+            .split_once('/')
+            .ok_or_else(|| format!("invalid subnet \"{s}\": expected CIDR notation"))?;
+        let address = address
+            .parse()
+            .map_err(|e| format!("invalid subnet \"{s}\": {e}"))?;
+        let prefix_len = prefix_len
+            .parse()
+            .map_err(|e| format!("invalid subnet \"{s}\": {e}"))?;
+        Subnet::new(address, prefix_len).map_err(|e| format!("invalid subnet \"{s}\": {e}"))
+    }
+}
+
+impl<'de> Deserialize<'de> for Subnet {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        String::deserialize(d)?.parse().map_err(D::Error::custom)
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Default, Clone, Debug)]
 pub struct Location {
+    #[schemars(with = "HashMap<String, String>")]
     pub latency_to: HashMap<CowStr, HumanReadable<Duration>>,
     pub packet_loss_to: HashMap<CowStr, f32>,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, JsonSchema, Clone, Debug)]
 pub struct Reliability {
+    #[schemars(with = "String")]
     pub added_latency: HumanReadable<Duration>,
     pub added_packet_loss: f32,
     pub bandwidth_up: CowStr,
     pub bandwidth_down: CowStr,
 }
 
-#[derive(Deserialize, Default, Clone, Debug)]
+#[derive(Deserialize, JsonSchema, Default, Clone, Debug)]
 #[serde(default)]
 pub struct Genesis {
     pub generator_image: Option<String>,
@@ -139,10 +413,39 @@ pub struct Genesis {
     pub chain_id: Option<u64>,
     pub deposit_contract_address: Option<String>,
     pub mnemonic: Option<String>,
-    pub capella_epoch: Option<u64>,
-    pub deneb_epoch: Option<u64>,
-    pub electra_epoch: Option<u64>,
-    pub eip7594_epoch: Option<u64>,
+    pub genesis_timestamp: Option<u64>,
+    /// Must be positive, as epochs are computed from it
+    pub slot_duration_in_seconds: Option<NonZeroU64>,
+    pub seconds_per_eth1_block: Option<u64>,
+    pub genesis_fork_version: Option<String>,
+    pub altair_fork_version: Option<String>,
+    pub bellatrix_fork_version: Option<String>,
+    pub capella_fork_version: Option<String>,
+    pub deneb_fork_version: Option<String>,
+    pub electra_fork_version: Option<String>,
+    #[serde(alias = "eip7594_fork_version")]
+    pub fulu_fork_version: Option<String>,
+    pub gloas_fork_version: Option<String>,
+    #[serde(rename = "capella", alias = "capella_epoch")]
+    pub capella_epoch: Option<ForkTime>,
+    #[serde(rename = "deneb", alias = "deneb_epoch")]
+    pub deneb_epoch: Option<ForkTime>,
+    #[serde(rename = "electra", alias = "electra_epoch")]
+    pub electra_epoch: Option<ForkTime>,
+    /// Also accepted as `osaka`
+    #[serde(
+        rename = "fulu",
+        alias = "osaka",
+        alias = "fulu_epoch",
+        alias = "eip7594_epoch",
+        alias = "eip7594"
+    )]
+    pub fulu_epoch: Option<ForkTime>,
+    /// Also accepted as `amsterdam`
+    #[serde(rename = "gloas", alias = "amsterdam", alias = "gloas_epoch")]
+    pub gloas_epoch: Option<ForkTime>,
+    /// Only warn instead of failing if a fork is scheduled after `general.stop_time`
+    pub allow_forks_after_stop_time: bool,
     pub withdrawal_address: Option<String>,
     pub delay: Option<u64>,
     pub gaslimit: Option<u64>,
@@ -156,17 +459,212 @@ pub struct Genesis {
     pub custody_requirement: Option<u64>,
     pub data_column_sidecar_subnet_count: Option<u64>,
     pub max_blobs_per_block: Option<u64>,
+    pub max_blobs_per_block_electra: Option<u64>,
+    pub target_blobs_per_block_electra: Option<u64>,
+    pub base_fee_update_fraction_electra: Option<u64>,
+    pub number_of_custody_groups: Option<u64>,
+    pub validator_custody_requirement: Option<u64>,
+    pub balance_per_additional_custody_group: Option<u64>,
+    /// Blob parameter only forks, i.e. changes of the blob parameters after Fulu.
+    pub blob_schedule: Vec<BlobParameters>,
     pub premine: Option<HashMap<String, String>>,
+    /// Accounts to add to the genesis state, e.g. preloaded contracts.
+    pub alloc: HashMap<String, AllocEntry>,
+    /// JSON files containing accounts to add to the genesis state, in the same format as `alloc`
+    /// and the `alloc` field of a `genesis.json`.
+    pub alloc_files: Vec<PathBuf>,
+    /// Additional chain spec keys, passed through to `values.env` as is. These take precedence
+    /// over the values generated from the other options.
+    #[schemars(with = "BTreeMap<String, serde_json::Value>")]
+    pub extra: BTreeMap<String, Value>,
 }
 
-#[derive(Default, Deserialize, Clone, Debug)]
+impl Genesis {
+    pub fn genesis_fork_version(&self) -> &str {
+        self.genesis_fork_version
+            .as_deref()
+            .unwrap_or(DEFAULT_GENESIS_FORK_VERSION)
+    }
+
+    pub fn slots_per_epoch(&self) -> u64 {
+        match self.preset_base.as_deref() {
+            Some("minimal") => 8,
+            _ => 32,
+        }
+    }
+
+    pub fn slot_duration(&self) -> Duration {
+        Duration::from_secs(self.slot_duration_in_seconds.map_or(12, NonZeroU64::get))
+    }
+
+    /// Simulated time (i.e. time since start of the simulation) at which genesis happens.
+    pub fn genesis_time(&self) -> Duration {
+        let timestamp = self.genesis_timestamp.unwrap_or(SHADOW_START_TIMESTAMP);
+        Duration::from_secs(
+            (timestamp + self.delay.unwrap_or(300)).saturating_sub(SHADOW_START_TIMESTAMP),
+        )
+    }
+
+    /// First epoch starting at or after the given point in time.
+    pub fn epoch_at(&self, time: ForkTime) -> u64 {
+        let slots_per_epoch = self.slots_per_epoch();
+        match time {
+            ForkTime::Epoch(epoch) => epoch,
+            ForkTime::Slot(slot) => slot.div_ceil(slots_per_epoch),
+            ForkTime::Time(time) => {
+                let since_genesis = time.saturating_sub(self.genesis_time()).as_secs();
+                let epoch_duration = self.slot_duration().as_secs() * slots_per_epoch;
+                since_genesis.div_ceil(epoch_duration)
+            }
+        }
+    }
+
+    /// Simulated time at which the given epoch starts.
+    pub fn epoch_start(&self, epoch: u64) -> Duration {
+        let epoch_duration = self.slot_duration().as_secs() * self.slots_per_epoch();
+        self.genesis_time() + Duration::from_secs(epoch.saturating_mul(epoch_duration))
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Default, Clone, Debug)]
+#[serde(default)]
+pub struct AllocEntry {
+    /// Balance in wei, as a decimal or hex string.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub storage: HashMap<String, String>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_nonce",
+        deserialize_with = "deserialize_nonce"
+    )]
+    #[schemars(schema_with = "integer_or_string")]
+    pub nonce: Option<u64>,
+}
+
+fn serialize_nonce<S: Serializer>(nonce: &Option<u64>, s: S) -> Result<S::Ok, S::Error> {
+    match nonce {
+        Some(nonce) => s.serialize_str(&format!("{nonce:#x}")),
+        None => s.serialize_none(),
+    }
+}
+
+fn deserialize_probability<'de, D: Deserializer<'de>>(d: D) -> Result<f64, D::Error> {
+    let probability = f64::deserialize(d)?;
+    if !(0.0..=1.0).contains(&probability) {
+        return Err(D::Error::custom(format!(
+            "probability {probability} is not between 0 and 1"
+        )));
+    }
+    Ok(probability)
+}
+
+fn deserialize_nonce<'de, D: Deserializer<'de>>(d: D) -> Result<Option<u64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Number(u64),
+        String(String),
+    }
+    match Raw::deserialize(d)? {
+        Raw::Number(nonce) => Ok(Some(nonce)),
+        Raw::String(s) => match s.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => s.parse(),
+        }
+        .map(Some)
+        .map_err(D::Error::custom),
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Clone, Debug)]
+pub struct BlobParameters {
+    pub epoch: ForkTime,
+    pub max_blobs_per_block: u64,
+    pub target_blobs_per_block: Option<u64>,
+    pub base_fee_update_fraction: Option<u64>,
+}
+
+/// A point in time at which a fork is scheduled. Can be specified as a raw epoch number
+/// (`electra: 10`), as a slot (`electra: slot 320`), or as simulated time (`electra: 20m`).
+/// Slots and times that do not fall on an epoch boundary are rounded up to the next epoch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForkTime {
+    Epoch(u64),
+    Slot(u64),
+    Time(Duration),
+}
+
+impl FromStr for ForkTime {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(epoch) = s.strip_prefix("epoch") {
+            epoch
+                .trim()
+                .parse()
+                .map(ForkTime::Epoch)
+                .map_err(|e| format!("invalid epoch \"{s}\": {e}"))
+        } else if let Some(slot) = s.strip_prefix("slot") {
+            slot.trim()
+                .parse()
+                .map(ForkTime::Slot)
+                .map_err(|e| format!("invalid slot \"{s}\": {e}"))
+        } else if let Ok(epoch) = s.parse() {
+            Ok(ForkTime::Epoch(epoch))
+        } else {
+            parse_duration(s)
+                .map(ForkTime::Time)
+                .map_err(|e| format!("invalid fork time \"{s}\": {e}"))
+        }
+    }
+}
+
+impl JsonSchema for ForkTime {
+    fn schema_name() -> String {
+        "ForkTime".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        integer_or_string(gen)
+    }
+}
+
+fn integer_or_string(_: &mut SchemaGenerator) -> Schema {
+    SchemaObject {
+        instance_type: Some(vec![InstanceType::Integer, InstanceType::String].into()),
+        ..Default::default()
+    }
+    .into()
+}
+
+impl<'de> Deserialize<'de> for ForkTime {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Epoch(u64),
+            String(String),
+        }
+        match Raw::deserialize(d)? {
+            Raw::Epoch(epoch) => Ok(ForkTime::Epoch(epoch)),
+            Raw::String(s) => s.parse().map_err(D::Error::custom),
+        }
+    }
+}
+
+#[derive(Default, Deserialize, JsonSchema, Clone, Debug)]
 pub enum Topology {
     #[default]
     Simple,
     Clustered(Vec<Cluster>),
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, JsonSchema, Clone, Debug)]
 pub struct Cluster {
     pub gateway_latency: u64,
     pub cluster_latencies: Vec<u64>,
@@ -349,6 +847,7 @@ impl EthShadowConfig {
         self.add_builtin_client("lighthouse", Lighthouse::default());
         self.add_builtin_client("lighthouse_vc", LighthouseValidatorClient::default());
         self.add_builtin_client("prometheus", Prometheus::default());
+        self.add_builtin_client("remote_write_file", RemoteWriteFile::default());
     }
 
     pub fn add_builtin_location<const N: usize>(
@@ -374,45 +873,34 @@ impl EthShadowConfig {
         self.clients.entry(name.into()).or_insert(Box::new(client));
     }
 
-    pub fn minimum_latency(&self) -> Duration {
-        self.locations
-            .values()
-            .flat_map(|loc| loc.latency_to.values())
-            .map(|latency| latency.into_inner())
-            .min()
-            .expect("latencies should be specified at this point")
-    }
-
-    pub fn desugar_nodes(&self) -> Result<Vec<Node>, Error> {
+    pub fn desugar_nodes(&self) -> Result<Vec<Node<'_>>, Error> {
         let mut result = vec![];
 
         for node in &self.nodes {
-            let clients: Vec<Vec<_>> = if !node.clients.is_empty() {
-                node.clients
-                    .values()
-                    .map(|clients| {
-                        clients
-                            .iter()
-                            .map(|client| {
-                                self.clients
-                                    .get(client.as_str())
-                                    .map(AsRef::as_ref)
-                                    .ok_or_else(|| Error::UnknownClient(client.clone()))
-                            })
-                            .try_collect()
-                    })
-                    .try_collect()?
-            } else {
-                self.default_clients
-                    .values()
-                    .map(|client| {
-                        self.clients
-                            .get(client)
-                            .map(|b| vec![b.as_ref()])
-                            .ok_or_else(|| Error::UnknownClient(client.to_string()))
-                    })
-                    .try_collect()?
-            };
+            let clients = self.node_clients(node)?;
+            if node.simple_boot_hosts {
+                let reliability = (&node.reliabilities)
+                    .into_iter()
+                    .next()
+                    .map_or("reliable", String::as_str);
+                for (location, count) in self.bootnodes.count_per_location() {
+                    for clients in clients
+                        .iter()
+                        .map(|vec| vec.iter().copied())
+                        .multi_cartesian_product()
+                    {
+                        result.push(Node {
+                            location,
+                            reliability,
+                            clients,
+                            count,
+                            tag: node.tag.as_deref(),
+                            subnet: node.subnet,
+                        });
+                    }
+                }
+                continue;
+            }
             for location in &node.locations {
                 for reliability in &node.reliabilities {
                     for clients in clients
@@ -426,6 +914,7 @@ impl EthShadowConfig {
                             clients,
                             count: node.count_per_combination()?,
                             tag: node.tag.as_deref(),
+                            subnet: node.subnet,
                         });
                     }
                 }
@@ -434,18 +923,53 @@ impl EthShadowConfig {
 
         Ok(result)
     }
+
+    /// Resolves the clients of a node, one list of alternatives per client kind.
+    #[allow(clippy::type_complexity)]
+    fn node_clients(&self, node: &SugaredNode) -> Result<Vec<Vec<(&str, &dyn Client)>>, Error> {
+        if !node.clients.is_empty() {
+            node.clients
+                .values()
+                .map(|clients| {
+                    clients
+                        .iter()
+                        .map(|client| {
+                            self.clients
+                                .get_key_value(client.as_str())
+                                .map(|(id, client)| (id.as_ref(), client.as_ref()))
+                                .ok_or_else(|| Error::UnknownClient(client.clone()))
+                        })
+                        .try_collect()
+                })
+                .try_collect()
+        } else {
+            self.default_clients
+                .values()
+                .map(|client| {
+                    self.clients
+                        .get_key_value(client)
+                        .map(|(id, client)| vec![(id.as_ref(), client.as_ref())])
+                        .ok_or_else(|| Error::UnknownClient(client.to_string()))
+                })
+                .try_collect()
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct Node<'a> {
     pub location: &'a str,
     pub reliability: &'a str,
-    pub clients: Vec<&'a dyn Client>,
+    /// The clients to run, along with their id in the `clients` section
+    pub clients: Vec<(&'a str, &'a dyn Client)>,
     pub count: usize,
     pub tag: Option<&'a str>,
+    /// Allocate the IPs from this subnet instead of using the allocation strategy
+    pub subnet: Option<Subnet>,
 }
 
-pub const DEFAULT_GENESIS_GEN_IMAGE: &str = "ethpandaops/ethereum-genesis-generator:3.3.7";
+pub const DEFAULT_GENESIS_GEN_IMAGE: &str = "ethpandaops/ethereum-genesis-generator:5.0.0";
+pub const DEFAULT_GENESIS_FORK_VERSION: &str = "0x10000000";
 pub const DEFAULT_MNEMONIC: &str = "\
 iron oxygen will win \
 iron oxygen will win \
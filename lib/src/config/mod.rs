@@ -2,18 +2,24 @@ use std::collections::VecDeque;
 use std::fs::File;
 use std::io::{Stdin, StdinLock};
 use std::net::TcpStream;
+use std::path::Path;
 use std::process::{ChildStderr, ChildStdout};
 use std::sync::Arc;
 
 use serde_yaml::{Mapping, Value};
 
 use crate::error::Error;
+pub use compose::load;
 pub use ethshadow::EthShadowConfig;
+pub use schema::json_schema;
 pub use shadow::ShadowConfig;
 
+mod compose;
 pub mod ethshadow;
 mod one_or_many;
+mod schema;
 pub mod shadow;
+mod shadow_schema;
 
 pub struct FullConfig {
     pub ethshadow_config: EthShadowConfig,
@@ -24,6 +30,9 @@ impl TryFrom<Mapping> for FullConfig {
     type Error = Error;
 
     fn try_from(mut mapping: Mapping) -> Result<Self, Self::Error> {
+        if mapping.contains_key(crate::sweep::SWEEP_KEY) {
+            return Err(Error::UnexpectedSweep);
+        }
         let ethshadow_config: EthShadowConfig = mapping
             .remove("ethereum")
             .map(serde_yaml::from_value)
@@ -40,7 +49,10 @@ impl TryFrom<Mapping> for FullConfig {
 impl TryFrom<Value> for FullConfig {
     type Error = Error;
 
-    fn try_from(value: Value) -> Result<Self, Self::Error> {
+    fn try_from(mut value: Value) -> Result<Self, Self::Error> {
+        // includes are relative to the working directory if we do not know the config's path
+        compose::resolve_includes(&mut value, Path::new("."), &mut vec![])?;
+        compose::substitute_env_vars(&mut value)?;
         let Value::Mapping(mapping) = value else {
             return Err(Error::ExpectedOtherType("<root>".to_string()));
         };
@@ -79,6 +91,14 @@ from_readers!(
     VecDeque<u8>,
 );
 
+impl TryFrom<&Path> for FullConfig {
+    type Error = Error;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        compose::load(path)?.try_into()
+    }
+}
+
 impl TryFrom<&str> for FullConfig {
     type Error = Error;
 
@@ -1,9 +1,10 @@
+use schemars::JsonSchema;
 use serde::Deserialize;
 use std::mem;
 use std::slice::Iter;
 use std::vec::IntoIter;
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, JsonSchema, Clone, Debug)]
 #[serde(untagged)]
 pub enum OneOrMany<T> {
     One(T),
@@ -84,7 +85,7 @@ impl<T> OneOrMany<T> {
         }
     }
 
-    pub(crate) fn iter(&self) -> OneOrManyIterRef<T> {
+    pub(crate) fn iter(&self) -> OneOrManyIterRef<'_, T> {
         self.into_iter()
     }
 }
@@ -0,0 +1,45 @@
+use crate::config::EthShadowConfig;
+use schemars::gen::SchemaSettings;
+use schemars::schema::{
+    InstanceType, Metadata, ObjectValidation, RootSchema, Schema, SchemaObject,
+};
+use schemars::Map;
+
+/// Generates a JSON schema for the config file. Only the `ethereum` section is described, all
+/// other sections are passed on to Shadow and are therefore allowed without further checks.
+pub fn json_schema() -> serde_json::Value {
+    let mut gen = SchemaSettings::draft07().into_generator();
+    let ethereum = gen.subschema_for::<EthShadowConfig>();
+    let mut definitions = gen.take_definitions();
+    add_fork_aliases(&mut definitions);
+    let schema = RootSchema {
+        meta_schema: gen.settings().meta_schema.clone(),
+        schema: SchemaObject {
+            metadata: Some(Box::new(Metadata {
+                title: Some("Ethshadow configuration".to_string()),
+                ..Default::default()
+            })),
+            instance_type: Some(InstanceType::Object.into()),
+            object: Some(Box::new(ObjectValidation {
+                properties: [("ethereum".to_string(), ethereum)].into_iter().collect(),
+                ..Default::default()
+            })),
+            ..Default::default()
+        },
+        definitions,
+    };
+    serde_json::to_value(schema).expect("schema is always serializable")
+}
+
+/// The execution layer names of the forks are accepted as aliases, so list them as well.
+fn add_fork_aliases(definitions: &mut Map<String, Schema>) {
+    let Some(Schema::Object(genesis)) = definitions.get_mut("Genesis") else {
+        return;
+    };
+    let properties = &mut genesis.object().properties;
+    for (alias, fork) in [("osaka", "fulu"), ("amsterdam", "gloas")] {
+        if let Some(schema) = properties.get(fork).cloned() {
+            properties.insert(alias.to_string(), schema);
+        }
+    }
+}
@@ -1,5 +1,8 @@
+use crate::config::shadow_schema;
 use crate::error::Error;
 use crate::CowStr;
+use humantime_serde::re::humantime::{format_duration, parse_duration};
+use log::warn;
 use serde::Serialize;
 use serde_yaml::mapping::IterMut;
 use serde_yaml::{to_value, Mapping, Value};
@@ -12,6 +15,8 @@ use std::time::Duration;
 /// The reason why we do not do this "properly" by defining a serde struct is that we want to
 /// support all the options, and that is too complex. Unfortunately, Shadow does not expose
 /// its config structs in a separate crate, and compiling all of Shadow for that is not nice.
+/// Instead, [`ShadowConfig::validate`] checks the options we know about against a description of
+/// Shadow's config, while keeping unknown options for forward compatibility.
 pub struct ShadowConfig(pub Mapping);
 
 #[derive(Serialize, Debug, Clone)]
@@ -31,6 +36,11 @@ pub struct Process {
 }
 
 impl ShadowConfig {
+    /// Checks the options known to us for validity, and warns about unknown options.
+    pub fn validate(&self) -> Result<(), Error> {
+        shadow_schema::validate(&self.0)
+    }
+
     pub fn general_mut(&mut self) -> Result<&mut Mapping, Error> {
         self.0
             .entry("general".into())
@@ -56,6 +66,15 @@ impl ShadowConfig {
             .unwrap_or(1)
     }
 
+    pub fn stop_time(&self) -> Result<Option<Duration>, Error> {
+        self.0
+            .get("general")
+            .and_then(Value::as_mapping)
+            .and_then(|m| m.get("stop_time"))
+            .map(|value| parse_time(value, "general.stop_time"))
+            .transpose()
+    }
+
     pub fn add_host(&mut self, hostname: String, host: &Host) -> Result<(), Error> {
         let hosts = self
             .0
@@ -87,7 +106,7 @@ impl ShadowConfig {
         }
     }
 
-    pub fn hosts_mut(&mut self) -> Result<HostsMut, Error> {
+    pub fn hosts_mut(&mut self) -> Result<HostsMut<'_>, Error> {
         Ok(HostsMut {
             hosts: self
                 .0
@@ -101,7 +120,7 @@ impl ShadowConfig {
         })
     }
 
-    pub fn apply_defaults(&mut self, runahead: Duration) -> Result<(), Error> {
+    pub fn apply_defaults(&mut self) -> Result<(), Error> {
         let general = self.general_mut()?;
         general
             .entry("model_unblocked_syscall_latency".into())
@@ -110,15 +129,60 @@ impl ShadowConfig {
             .entry("heartbeat_interval".into())
             .or_insert_with(|| Value::String("1m".into()));
         let experimental = self.experimental_mut()?;
-        // todo: warn user if they override runahead with a value larger than the min latency
-        experimental
-            .entry("runahead".into())
-            .or_insert_with(|| format!("{}ns", runahead.as_nanos()).into());
         experimental
             .entry("use_memory_manager".into())
             .or_insert(Value::Bool(true));
         Ok(())
     }
+
+    /// Sets the runahead to the given minimum latency, or checks that the runahead configured by
+    /// the user does not exceed it. Larger values might cause Shadow to deliver packets late.
+    pub fn apply_runahead(
+        &mut self,
+        minimum_latency: Duration,
+        allow_larger: bool,
+    ) -> Result<(), Error> {
+        let experimental = self.experimental_mut()?;
+        match experimental.get("runahead") {
+            None => {
+                experimental.insert(
+                    "runahead".into(),
+                    format!("{}ns", minimum_latency.as_nanos()).into(),
+                );
+            }
+            // Shadow derives the runahead from the graph itself
+            Some(Value::Null) => {}
+            Some(value) => {
+                let runahead = parse_time(value, "experimental.runahead")?;
+                if runahead > minimum_latency {
+                    if !allow_larger {
+                        return Err(Error::RunaheadTooLarge(runahead, minimum_latency));
+                    }
+                    warn!(
+                        "The runahead of {} exceeds the minimum latency of {}",
+                        format_duration(runahead),
+                        format_duration(minimum_latency),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a time value as accepted by Shadow: either an integer number of seconds, or a string
+/// with units such as "10 min".
+pub(crate) fn parse_time(value: &Value, name: &str) -> Result<Duration, Error> {
+    match value {
+        Value::Number(n) => n
+            .as_u64()
+            .map(Duration::from_secs)
+            .ok_or_else(|| Error::InvalidTime(name.to_string(), n.to_string())),
+        Value::String(s) => {
+            parse_duration(s).map_err(|e| Error::InvalidTime(name.to_string(), e.to_string()))
+        }
+        _ => Err(Error::ExpectedOtherType(name.to_string())),
+    }
 }
 
 pub struct HostsMut<'a> {
@@ -0,0 +1,233 @@
+//! A description of the Shadow configuration options, used to validate the user's config before
+//! spending time on generation. We only check the known keys and their types - Shadow still has
+//! the final say. Unknown keys are passed through with a warning, so that options added in newer
+//! Shadow versions can still be used.
+
+use crate::config::shadow::parse_time;
+use crate::Error;
+use log::warn;
+use serde_yaml::{Mapping, Value};
+
+type Opt = (&'static str, Kind);
+
+#[derive(Clone, Copy, Debug)]
+enum Kind {
+    Bool,
+    Integer,
+    /// Seconds as integer, or a string with units, e.g. "10 min"
+    Time,
+    /// Bytes as integer, or a string with units, e.g. "16 KiB"
+    Bytes,
+    String,
+    Choice(&'static [&'static str]),
+    Any,
+    Section(&'static [Opt]),
+    /// A mapping with arbitrary keys and values of the given kind
+    MapOf(&'static Kind),
+    SequenceOf(&'static Kind),
+    Either(&'static Kind, &'static Kind),
+}
+
+const LOG_LEVELS: &[&str] = &["error", "warning", "info", "debug", "trace"];
+
+const ROOT: &[Opt] = &[
+    ("general", Kind::Section(GENERAL)),
+    ("network", Kind::Section(NETWORK)),
+    ("experimental", Kind::Section(EXPERIMENTAL)),
+    ("host_option_defaults", Kind::Section(HOST_OPTIONS)),
+    ("hosts", Kind::MapOf(&Kind::Section(HOST))),
+];
+
+const GENERAL: &[Opt] = &[
+    ("bootstrap_end_time", Kind::Time),
+    ("data_directory", Kind::String),
+    ("heartbeat_interval", Kind::Time),
+    ("log_level", Kind::Choice(LOG_LEVELS)),
+    ("model_unblocked_syscall_latency", Kind::Bool),
+    ("parallelism", Kind::Integer),
+    ("progress", Kind::Bool),
+    ("seed", Kind::Integer),
+    ("stop_time", Kind::Time),
+    ("template_directory", Kind::String),
+];
+
+const NETWORK: &[Opt] = &[("graph", Kind::Any), ("use_shortest_path", Kind::Bool)];
+
+const EXPERIMENTAL: &[Opt] = &[
+    ("host_heartbeat_interval", Kind::Time),
+    ("host_heartbeat_log_info", Kind::SequenceOf(&Kind::String)),
+    ("host_heartbeat_log_level", Kind::Choice(LOG_LEVELS)),
+    ("interface_qdisc", Kind::Choice(&["fifo", "round-robin"])),
+    ("log_errors_to_tty", Kind::Bool),
+    ("max_unapplied_cpu_latency", Kind::Time),
+    ("native_preemption_enabled", Kind::Bool),
+    ("native_preemption_native_interval", Kind::Time),
+    ("native_preemption_sim_interval", Kind::Time),
+    ("report_errors_to_stderr", Kind::Bool),
+    ("runahead", Kind::Time),
+    (
+        "scheduler",
+        Kind::Choice(&["thread-per-core", "thread-per-host"]),
+    ),
+    ("socket_recv_autotune", Kind::Bool),
+    ("socket_recv_buffer", Kind::Bytes),
+    ("socket_send_autotune", Kind::Bool),
+    ("socket_send_buffer", Kind::Bytes),
+    (
+        "strace_logging_mode",
+        Kind::Choice(&["off", "standard", "deterministic"]),
+    ),
+    ("unblocked_syscall_latency", Kind::Time),
+    ("unblocked_vdso_latency", Kind::Time),
+    ("use_cpu_pinning", Kind::Bool),
+    ("use_dynamic_runahead", Kind::Bool),
+    ("use_memory_manager", Kind::Bool),
+    ("use_new_tcp", Kind::Bool),
+    ("use_object_counters", Kind::Bool),
+    ("use_preload_libc", Kind::Bool),
+    ("use_preload_openssl_crypto", Kind::Bool),
+    ("use_preload_openssl_rng", Kind::Bool),
+    ("use_sched_fifo", Kind::Bool),
+    ("use_syscall_counters", Kind::Bool),
+    ("use_worker_spinning", Kind::Bool),
+];
+
+const HOST_OPTIONS: &[Opt] = &[
+    ("log_level", Kind::Choice(LOG_LEVELS)),
+    ("pcap_capture_size", Kind::Bytes),
+    ("pcap_enabled", Kind::Bool),
+];
+
+const HOST: &[Opt] = &[
+    ("bandwidth_down", Kind::String),
+    ("bandwidth_up", Kind::String),
+    ("host_options", Kind::Section(HOST_OPTIONS)),
+    ("ip_addr", Kind::String),
+    // ethshadow specific: replaced by network_node_id during generation
+    ("location", Kind::String),
+    ("network_node_id", Kind::Integer),
+    ("processes", Kind::SequenceOf(&Kind::Section(PROCESS))),
+    // ethshadow specific: replaced by network_node_id during generation
+    ("reliability", Kind::String),
+];
+
+const PROCESS: &[Opt] = &[
+    (
+        "args",
+        Kind::Either(&Kind::String, &Kind::SequenceOf(&Kind::String)),
+    ),
+    ("environment", Kind::MapOf(&Kind::String)),
+    ("expected_final_state", Kind::Any),
+    ("path", Kind::String),
+    (
+        "shutdown_signal",
+        Kind::Either(&Kind::String, &Kind::Integer),
+    ),
+    ("shutdown_time", Kind::Time),
+    ("start_time", Kind::Time),
+];
+
+/// Validates the given Shadow config (without the `ethereum` section). Returns an error if a known
+/// option has an invalid value, and logs a warning for each unknown option.
+pub fn validate(config: &Mapping) -> Result<(), Error> {
+    check_section(config, ROOT, "")
+}
+
+fn check(value: &Value, kind: Kind, path: &str) -> Result<(), Error> {
+    let valid = match (kind, value) {
+        // unset values are equivalent to omitting them
+        (_, Value::Null) | (Kind::Any, _) => true,
+        (Kind::Bool, value) => value.is_bool(),
+        (Kind::Integer, value) => value.is_u64(),
+        (Kind::Time, value) => return parse_time(value, path).map(|_| ()),
+        (Kind::Bytes, value) => value.is_u64() || value.is_string(),
+        (Kind::String, value) => value.is_string(),
+        (Kind::Choice(choices), Value::String(s)) => choices.contains(&s.as_str()),
+        (Kind::Section(opts), Value::Mapping(mapping)) => {
+            return check_section(mapping, opts, path)
+        }
+        (Kind::MapOf(kind), Value::Mapping(mapping)) => {
+            for (key, value) in mapping {
+                check(value, *kind, &join(path, &key_name(key)))?;
+            }
+            true
+        }
+        (Kind::SequenceOf(kind), Value::Sequence(seq)) => {
+            for (idx, value) in seq.iter().enumerate() {
+                check(value, *kind, &format!("{path}[{idx}]"))?;
+            }
+            true
+        }
+        (Kind::Either(a, b), value) => {
+            check(value, *a, path).is_ok() || check(value, *b, path).is_ok()
+        }
+        _ => false,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidShadowOption(
+            path.to_string(),
+            kind.describe(),
+        ))
+    }
+}
+
+fn check_section(mapping: &Mapping, opts: &[Opt], path: &str) -> Result<(), Error> {
+    for (key, value) in mapping {
+        let key = key_name(key);
+        let option = join(path, &key);
+        match opts.iter().find(|(name, _)| *name == key) {
+            Some((_, kind)) => check(value, *kind, &option)?,
+            None => {
+                let suggestion = opts
+                    .iter()
+                    .map(|(name, _)| (name, strsim::levenshtein(name, &key)))
+                    .filter(|(_, distance)| *distance <= 2)
+                    .min_by_key(|(_, distance)| *distance);
+                match suggestion {
+                    Some((name, _)) => warn!(
+                        "Unknown Shadow option {option}, did you mean {}?",
+                        join(path, name)
+                    ),
+                    None => warn!("Unknown Shadow option {option}"),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn key_name(key: &Value) -> String {
+    match key {
+        Value::String(s) => s.clone(),
+        other => serde_yaml::to_string(other)
+            .map(|s| s.trim_end().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+impl Kind {
+    fn describe(self) -> String {
+        match self {
+            Kind::Bool => "a boolean".to_string(),
+            Kind::Integer => "a non-negative integer".to_string(),
+            Kind::Time => "a time, e.g. \"10 min\"".to_string(),
+            Kind::Bytes => "a size, e.g. \"16 KiB\"".to_string(),
+            Kind::String => "a string".to_string(),
+            Kind::Choice(choices) => format!("one of {}", choices.join(", ")),
+            Kind::Any => "any value".to_string(),
+            Kind::Section(_) | Kind::MapOf(_) => "a mapping".to_string(),
+            Kind::SequenceOf(_) => "a list".to_string(),
+            Kind::Either(a, b) => format!("{} or {}", a.describe(), b.describe()),
+        }
+    }
+}
@@ -1,12 +1,16 @@
-use std::env::VarError;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("failed to parse/write config: {0}")]
     Parse(#[from] serde_yaml::Error),
+    #[error("failed to parse/write JSON: {0}")]
+    Json(#[from] serde_json::Error),
     #[error("value of {0} has wrong YAML type")]
     ExpectedOtherType(String),
+    #[error("Invalid value for Shadow option {0}, expected {1}")]
+    InvalidShadowOption(String, String),
     #[error("name in your config conflicts with generated config: {0}")]
     NameConflict(String),
     #[error("I/O Error: {0}")]
@@ -42,8 +46,61 @@ pub enum Error {
                 combinations ({1})"
     )]
     InconsistentCount(usize, usize),
+    #[error("Invalid time for {0}: {1}")]
+    InvalidTime(String, String),
+    #[error(
+        "The runahead of {0:?} exceeds the minimum latency of {1:?} in the network graph. Set \
+            ethereum.allow_large_runahead if you really want this"
+    )]
+    RunaheadTooLarge(Duration, Duration),
     #[error("Missing env var: {0}")]
-    MissingEnvVar(#[from] VarError),
+    MissingEnvVar(String),
+    #[error("Invalid env var reference in \"{0}\"")]
+    InvalidEnvVarReference(String),
+    #[error("Unable to read included config {0}: {1}")]
+    Include(String, std::io::Error),
+    #[error("Config file {0} includes itself")]
+    IncludeCycle(String),
+    #[error("Invalid sweep path {0}: it must point into a mapping or to an existing list element")]
+    InvalidSweepPath(String),
+    #[error("Invalid sweep range {0}: expected {{range: [start, end]}} with start <= end and at most {} values", crate::sweep::MAX_RANGE_LEN)]
+    InvalidSweepRange(String),
+    #[error("The existing sweep manifest lists {0} with different parameters, use a new output directory")]
+    SweepChanged(String),
+    #[error("The config contains a sweep section, use `ethshadow sweep` to generate it")]
+    UnexpectedSweep,
+    #[error("Unable to find {0} in values.env")]
+    MissingGenesisValue(String),
+    #[error("The simulation has no Prometheus instance")]
+    NoPrometheus,
+    #[error("Prometheus {0}")]
+    Prometheus(String),
+    #[error("Aggregating Prometheus instance found no sharded Prometheus instances")]
+    NoPrometheusShards,
+    #[error("Invalid Prometheus shard: {0}")]
+    InvalidShard(String),
+    #[error("Invalid Prometheus rule file {0}")]
+    InvalidRuleFile(String),
+    #[error("Multiple Prometheus rule files are named {0}")]
+    DuplicateRuleFile(String),
+    #[error("Invalid remote write request: {0}")]
+    InvalidRemoteWrite(String),
+    #[error("Invalid blob schedule: {0}")]
+    InvalidBlobSchedule(String),
+    #[error("{0}, set `allow_forks_after_stop_time` if this is intended")]
+    ForkAfterStopTime(String),
+    #[error("Invalid fork version {0}, expected 4 hex encoded bytes")]
+    InvalidForkVersion(String),
+    #[error("Unable to create ENR: {0}")]
+    Enr(#[from] enr::EnrError),
+    #[error("Invalid subnet: {0}")]
+    InvalidSubnet(String),
+    #[error("No free addresses left in {0}")]
+    SubnetExhausted(String),
+    #[error("IPv6-only hosts are not supported by Shadow yet, use `dual_stack` instead")]
+    Ipv6OnlyUnsupported,
+    #[error("No node with the tag \"{0}\" runs a consensus client to attack")]
+    NoVictims(String),
     #[error("Output data folder already exists")]
     OutputFolderExists,
     #[error(
@@ -0,0 +1,389 @@
+//! Export of the metrics captured by the in-simulation Prometheus instances.
+//!
+//! We start a local Prometheus on a copy of each recorded TSDB and query it via the HTTP API, as
+//! the TSDB format is not meant to be read by other tools. Prometheus compacts and rewrites the
+//! TSDB it is started on, so the simulation's data is never touched. Timestamps are converted to
+//! simulated time, i.e. seconds since the start of the simulation. Series are written as CSV.
+
+use crate::config::shadow::parse_time;
+use crate::genesis::SHADOW_START_TIMESTAMP;
+use crate::manifest::{Manifest, MANIFEST_FILE};
+use crate::Error;
+use log::{debug, info};
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{copy, create_dir_all, read_dir, read_to_string, write, File};
+use std::io::{BufWriter, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+/// Prometheus refuses queries resulting in more points per series.
+const MAX_POINTS_PER_QUERY: u64 = 10_000;
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(120);
+/// The port we picked may be taken by someone else before Prometheus binds it, so we retry.
+const STARTUP_ATTEMPTS: usize = 5;
+
+pub struct ExportOptions {
+    /// PromQL queries to export
+    pub queries: Vec<String>,
+    /// Resolution of the exported series
+    pub step: Duration,
+    pub output: PathBuf,
+    pub prometheus_executable: String,
+}
+
+#[derive(Deserialize)]
+struct QueryResponse {
+    status: String,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    data: Option<QueryData>,
+}
+
+#[derive(Deserialize)]
+struct QueryData {
+    result: Vec<Series>,
+}
+
+#[derive(Deserialize)]
+struct Series {
+    metric: BTreeMap<String, String>,
+    values: Vec<(f64, String)>,
+}
+
+/// Exports the given queries from each Prometheus instance of the simulation in `dir`. Returns
+/// the written files.
+pub fn export(dir: &Path, options: &ExportOptions) -> Result<Vec<PathBuf>, Error> {
+    let manifest: Manifest = serde_json::from_reader(File::open(dir.join(MANIFEST_FILE))?)?;
+    let instances: Vec<_> = manifest
+        .hosts
+        .iter()
+        .filter(|host| {
+            host.clients
+                .iter()
+                .any(|client| client.client_type == "prometheus")
+        })
+        .collect();
+    if instances.is_empty() {
+        return Err(Error::NoPrometheus);
+    }
+    let end = simulation_end(dir)?;
+
+    let mut written = vec![];
+    for instance in &instances {
+        let output = if instances.len() == 1 {
+            options.output.clone()
+        } else {
+            options.output.join(&instance.name)
+        };
+        create_dir_all(&output)?;
+        info!("Exporting metrics of {}", instance.name);
+        let prometheus = LocalPrometheus::start(
+            &options.prometheus_executable,
+            &instance.directory.join("prometheus"),
+        )?;
+
+        let mut index = BTreeMap::new();
+        for query in &options.queries {
+            let mut series = prometheus.query_range(query, end, options.step)?;
+            for series in &mut series {
+                add_host_labels(series, &manifest);
+            }
+            let file_name = format!("{}.csv", file_name_for(query));
+            write_series(&output.join(&file_name), &series)?;
+            written.push(output.join(&file_name));
+            index.insert(file_name, query.clone());
+        }
+        write(
+            output.join("queries.json"),
+            serde_json::to_string_pretty(&index)?,
+        )?;
+    }
+    Ok(written)
+}
+
+/// The configured stop time of the simulation.
+pub(crate) fn simulation_end(dir: &Path) -> Result<Duration, Error> {
+    let config: Value = serde_yaml::from_reader(File::open(dir.join("shadow.yaml"))?)?;
+    let stop_time = config
+        .get("general")
+        .and_then(|general| general.get("stop_time"))
+        .ok_or_else(|| {
+            Error::InvalidTime("general.stop_time".to_string(), "missing".to_string())
+        })?;
+    parse_time(stop_time, "general.stop_time")
+}
+
+/// Adds labels describing the scraped host for series lacking them, e.g. if the TSDB was created
+/// by an older version of ethshadow. Hosts are identified by the IP of the `instance` label.
+fn add_host_labels(series: &mut Series, manifest: &Manifest) {
+    if series.metric.contains_key("host") {
+        return;
+    }
+    let Some(ip) = series
+        .metric
+        .get("instance")
+        .and_then(|instance| instance.split(':').next())
+        .and_then(|ip| ip.parse::<Ipv4Addr>().ok())
+    else {
+        return;
+    };
+    if let Some(host) = manifest.hosts.iter().find(|host| host.ip == ip) {
+        series.metric.insert("host".to_string(), host.name.clone());
+        series
+            .metric
+            .insert("location".to_string(), host.location.clone());
+        series
+            .metric
+            .insert("reliability".to_string(), host.reliability.clone());
+        if let Some(tag) = &host.tag {
+            series.metric.insert("tag".to_string(), tag.clone());
+        }
+    }
+}
+
+struct LocalPrometheus {
+    child: Child,
+    port: u16,
+    // keep the copy of the TSDB until Prometheus is stopped
+    _tsdb: TempDir,
+}
+
+impl LocalPrometheus {
+    fn start(executable: &str, tsdb: &Path) -> Result<Self, Error> {
+        let copy = TempDir::new()?;
+        debug!("Copying {} to {}", tsdb.display(), copy.path().display());
+        copy_dir(tsdb, &copy.path().join("tsdb"))?;
+        let mut attempt = 1;
+        loop {
+            match Self::try_start(executable, copy.path()) {
+                Ok((child, port)) => {
+                    return Ok(LocalPrometheus {
+                        child,
+                        port,
+                        _tsdb: copy,
+                    })
+                }
+                Err(StartupError::PortTaken) if attempt < STARTUP_ATTEMPTS => {
+                    debug!("Port was taken, retrying");
+                    attempt += 1;
+                }
+                Err(StartupError::PortTaken) => {
+                    return Err(Error::Prometheus("unable to find a free port".to_string()))
+                }
+                Err(StartupError::Other(e)) => return Err(e),
+            }
+        }
+    }
+
+    /// Starts Prometheus on the TSDB in `dir`, logging to a file next to it.
+    fn try_start(executable: &str, dir: &Path) -> Result<(Child, u16), StartupError> {
+        // let the OS pick a free port
+        let port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .and_then(|listener| listener.local_addr())
+            .map_err(Error::from)?
+            .port();
+        let log = dir.join("prometheus.log");
+        let mut command = Command::new(executable);
+        command
+            .arg(format!(
+                "--storage.tsdb.path={}",
+                dir.join("tsdb").display()
+            ))
+            .arg("--storage.tsdb.retention.time=100y")
+            .arg("--config.file=/dev/null")
+            .arg(format!("--web.listen-address=127.0.0.1:{port}"))
+            .stdout(Stdio::null())
+            .stderr(File::create(&log).map_err(Error::from)?);
+        debug!("Invoking: {command:?}");
+        let mut child = command.spawn().map_err(Error::from)?;
+
+        // replaying the WAL may take a while
+        let start = Instant::now();
+        loop {
+            if let Some(status) = child.try_wait().map_err(Error::from)? {
+                let stderr = read_to_string(&log).unwrap_or_default();
+                if stderr.contains("address already in use") {
+                    return Err(StartupError::PortTaken);
+                }
+                return Err(Error::Prometheus(format!(
+                    "exited during startup: {status}: {}",
+                    stderr.trim().lines().last().unwrap_or_default()
+                ))
+                .into());
+            }
+            if matches!(get(port, "/-/ready"), Ok((200, _))) {
+                return Ok((child, port));
+            }
+            if start.elapsed() > STARTUP_TIMEOUT {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(Error::Prometheus("timed out during startup".to_string()).into());
+            }
+            sleep(Duration::from_millis(250));
+        }
+    }
+
+    /// Queries the range from the start of the simulation to `end`, split into multiple requests
+    /// if necessary.
+    fn query_range(
+        &self,
+        query: &str,
+        end: Duration,
+        step: Duration,
+    ) -> Result<Vec<Series>, Error> {
+        let step = step.as_secs().max(1);
+        let end = SHADOW_START_TIMESTAMP + end.as_secs();
+        let mut merged: BTreeMap<BTreeMap<String, String>, Vec<(f64, String)>> = BTreeMap::new();
+        let mut start = SHADOW_START_TIMESTAMP;
+        while start <= end {
+            let chunk_end = end.min(start + step * (MAX_POINTS_PER_QUERY - 1));
+            let path = format!(
+                "/api/v1/query_range?query={}&start={start}&end={chunk_end}&step={step}",
+                percent_encode(query)
+            );
+            let (_, body) = get(self.port, &path)?;
+            let response: QueryResponse = serde_json::from_str(&body)?;
+            if response.status != "success" {
+                return Err(Error::Prometheus(format!(
+                    "query \"{query}\" failed: {}",
+                    response.error.unwrap_or(response.status)
+                )));
+            }
+            for series in response.data.map(|data| data.result).unwrap_or_default() {
+                merged
+                    .entry(series.metric)
+                    .or_default()
+                    .extend(series.values);
+            }
+            start = chunk_end + step;
+        }
+        Ok(merged
+            .into_iter()
+            .map(|(metric, values)| Series { metric, values })
+            .collect())
+    }
+}
+
+impl Drop for LocalPrometheus {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+enum StartupError {
+    PortTaken,
+    Other(Error),
+}
+
+impl From<Error> for StartupError {
+    fn from(e: Error) -> Self {
+        StartupError::Other(e)
+    }
+}
+
+/// A minimal HTTP/1.0 GET, so that the response is neither chunked nor kept alive.
+fn get(port: u16, path: &str) -> Result<(u16, String), Error> {
+    let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port))?;
+    write!(stream, "GET {path} HTTP/1.0\r\nHost: localhost\r\n\r\n")?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| Error::Prometheus("invalid HTTP response".to_string()))?;
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| Error::Prometheus("invalid HTTP response".to_string()))?;
+    Ok((status, body.to_string()))
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<(), Error> {
+    create_dir_all(to)?;
+    for entry in read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_series(path: &Path, series: &[Series]) -> Result<(), Error> {
+    let labels: BTreeSet<&str> = series
+        .iter()
+        .flat_map(|series| series.metric.keys().map(String::as_str))
+        .collect();
+    let mut file = BufWriter::new(File::create(path)?);
+    write!(file, "time,value")?;
+    for label in &labels {
+        write!(file, ",{}", csv_field(label))?;
+    }
+    writeln!(file)?;
+    for series in series {
+        for (timestamp, value) in &series.values {
+            write!(
+                file,
+                "{},{value}",
+                timestamp - SHADOW_START_TIMESTAMP as f64
+            )?;
+            for label in &labels {
+                let value = series.metric.get(*label).map(String::as_str).unwrap_or("");
+                write!(file, ",{}", csv_field(value))?;
+            }
+            writeln!(file)?;
+        }
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// Derives a file name from a query by replacing all special characters.
+pub fn file_name_for(query: &str) -> String {
+    let mut name = String::with_capacity(query.len());
+    for c in query.chars() {
+        if c.is_ascii_alphanumeric() {
+            name.push(c);
+        } else if !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    let name = name.trim_matches('_');
+    let name = &name[..name.len().min(100)];
+    if name.is_empty() {
+        "query".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
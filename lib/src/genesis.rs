@@ -1,20 +1,57 @@
-use crate::config::ethshadow::{Genesis, DEFAULT_MNEMONIC};
+use crate::config::ethshadow::{AllocEntry, ForkTime, Genesis, DEFAULT_MNEMONIC};
 use crate::utils::log_and_wait;
 use crate::Error;
+use humantime_serde::re::humantime::format_duration;
+use log::{info, warn};
+use serde_yaml::Value;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Display;
 use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::io::{BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::Duration;
 use users::get_current_uid;
 
-pub const GENESIS_FORK_VERSION: &str = "0x10000000";
+/// Shadow starts all simulations at 2000-01-01 00:00 UTC.
+pub const SHADOW_START_TIMESTAMP: u64 = 946_684_800;
+
+const MAX_BLOB_PARAMETER_FORKS: usize = 5;
 
 pub fn write_config(
     genesis: &Genesis,
     num_validators: usize,
+    stop_time: Option<Duration>,
     mut output_path: PathBuf,
 ) -> Result<(), Error> {
+    validate_blob_schedule(genesis)?;
+    // resolve the forks first, so that we do not leave a partial file behind on errors
+    let capella_epoch = fork_epoch(genesis, "Capella", genesis.capella_epoch, 0, stop_time)?;
+    let deneb_epoch = fork_epoch(genesis, "Deneb", genesis.deneb_epoch, 0, stop_time)?;
+    let electra_epoch = fork_epoch(
+        genesis,
+        "Electra",
+        genesis.electra_epoch,
+        9_999_999,
+        stop_time,
+    )?;
+    let fulu_epoch = fork_epoch(genesis, "Fulu", genesis.fulu_epoch, 99_999_999, stop_time)?;
+    let gloas_epoch = fork_epoch(
+        genesis,
+        "Gloas",
+        genesis.gloas_epoch,
+        999_999_999,
+        stop_time,
+    )?;
+    let blob_schedule_epochs = genesis
+        .blob_schedule
+        .iter()
+        .enumerate()
+        .map(|(idx, params)| {
+            let name = format!("BPO_{}", idx + 1);
+            fork_epoch(genesis, &name, Some(params.epoch), 0, stop_time)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
     output_path.push("values.env");
     let mut file = BufWriter::new(File::create_new(output_path)?);
     let file = &mut file;
@@ -39,43 +76,92 @@ pub fn write_config(
         genesis.mnemonic.as_deref().unwrap_or(DEFAULT_MNEMONIC),
     )?;
     export(file, "CL_EXEC_BLOCK", "0")?;
-    export(file, "SLOT_DURATION_IN_SECONDS", "12")?;
+    export(
+        file,
+        "SLOT_DURATION_IN_SECONDS",
+        genesis.slot_duration().as_secs(),
+    )?;
+    export_optional(
+        file,
+        "SECONDS_PER_ETH1_BLOCK",
+        genesis.seconds_per_eth1_block,
+    )?;
     export(
         file,
         "DEPOSIT_CONTRACT_BLOCK",
         "0x0000000000000000000000000000000000000000000000000000000000000000",
     )?;
     export(file, "NUMBER_OF_VALIDATORS", num_validators)?;
-    export(file, "GENESIS_FORK_VERSION", "0x10000000")?;
-    export(file, "ALTAIR_FORK_VERSION", "0x20000000")?;
-    export(file, "BELLATRIX_FORK_VERSION", "0x30000000")?;
-    export(file, "CAPELLA_FORK_VERSION", "0x40000000")?;
+    export(file, "GENESIS_FORK_VERSION", genesis.genesis_fork_version())?;
+    export(
+        file,
+        "ALTAIR_FORK_VERSION",
+        genesis
+            .altair_fork_version
+            .as_deref()
+            .unwrap_or("0x20000000"),
+    )?;
     export(
         file,
-        "CAPELLA_FORK_EPOCH",
-        genesis.capella_epoch.unwrap_or(0),
+        "BELLATRIX_FORK_VERSION",
+        genesis
+            .bellatrix_fork_version
+            .as_deref()
+            .unwrap_or("0x30000000"),
     )?;
-    export(file, "DENEB_FORK_VERSION", "0x50000000")?;
-    export(file, "DENEB_FORK_EPOCH", genesis.deneb_epoch.unwrap_or(0))?;
-    export(file, "ELECTRA_FORK_VERSION", "0x60000000")?;
     export(
         file,
-        "ELECTRA_FORK_EPOCH",
-        genesis.electra_epoch.unwrap_or(9_999_999),
+        "CAPELLA_FORK_VERSION",
+        genesis
+            .capella_fork_version
+            .as_deref()
+            .unwrap_or("0x40000000"),
     )?;
-    export(file, "EIP7594_FORK_VERSION", "0x70000000")?;
+    export(file, "CAPELLA_FORK_EPOCH", capella_epoch)?;
     export(
         file,
-        "EIP7594_FORK_EPOCH",
-        genesis.eip7594_epoch.unwrap_or(99_999_999),
+        "DENEB_FORK_VERSION",
+        genesis
+            .deneb_fork_version
+            .as_deref()
+            .unwrap_or("0x50000000"),
     )?;
+    export(file, "DENEB_FORK_EPOCH", deneb_epoch)?;
+    export(
+        file,
+        "ELECTRA_FORK_VERSION",
+        genesis
+            .electra_fork_version
+            .as_deref()
+            .unwrap_or("0x60000000"),
+    )?;
+    export(file, "ELECTRA_FORK_EPOCH", electra_epoch)?;
+    export(
+        file,
+        "FULU_FORK_VERSION",
+        genesis.fulu_fork_version.as_deref().unwrap_or("0x70000000"),
+    )?;
+    export(file, "FULU_FORK_EPOCH", fulu_epoch)?;
+    export(
+        file,
+        "GLOAS_FORK_VERSION",
+        genesis
+            .gloas_fork_version
+            .as_deref()
+            .unwrap_or("0x80000000"),
+    )?;
+    export(file, "GLOAS_FORK_EPOCH", gloas_epoch)?;
     export(file, "WITHDRAWAL_TYPE", "0x01")?;
     export(
         file,
         "WITHDRAWAL_ADDRESS",
         genesis.withdrawal_address.as_deref().unwrap_or(""),
     )?;
-    export(file, "GENESIS_TIMESTAMP", "946684800")?;
+    export(
+        file,
+        "GENESIS_TIMESTAMP",
+        genesis.genesis_timestamp.unwrap_or(SHADOW_START_TIMESTAMP),
+    )?;
     export(file, "GENESIS_DELAY", genesis.delay.unwrap_or(300))?;
     export(
         file,
@@ -108,6 +194,61 @@ pub fn write_config(
         genesis.data_column_sidecar_subnet_count,
     )?;
     export_optional(file, "MAX_BLOBS_PER_BLOCK", genesis.max_blobs_per_block)?;
+    export_optional(
+        file,
+        "MAX_BLOBS_PER_BLOCK_ELECTRA",
+        genesis.max_blobs_per_block_electra,
+    )?;
+    export_optional(
+        file,
+        "TARGET_BLOBS_PER_BLOCK_ELECTRA",
+        genesis.target_blobs_per_block_electra,
+    )?;
+    export_optional(
+        file,
+        "BASE_FEE_UPDATE_FRACTION_ELECTRA",
+        genesis.base_fee_update_fraction_electra,
+    )?;
+    export_optional(
+        file,
+        "NUMBER_OF_CUSTODY_GROUPS",
+        genesis.number_of_custody_groups,
+    )?;
+    export_optional(
+        file,
+        "VALIDATOR_CUSTODY_REQUIREMENT",
+        genesis.validator_custody_requirement,
+    )?;
+    export_optional(
+        file,
+        "BALANCE_PER_ADDITIONAL_CUSTODY_GROUP",
+        genesis.balance_per_additional_custody_group,
+    )?;
+
+    for (idx, (params, epoch)) in genesis
+        .blob_schedule
+        .iter()
+        .zip(blob_schedule_epochs)
+        .enumerate()
+    {
+        let name = format!("BPO_{}", idx + 1);
+        export(file, &format!("{name}_EPOCH"), epoch)?;
+        export(
+            file,
+            &format!("{name}_MAX_BLOBS"),
+            params.max_blobs_per_block,
+        )?;
+        export_optional(
+            file,
+            &format!("{name}_TARGET_BLOBS"),
+            params.target_blobs_per_block,
+        )?;
+        export_optional(
+            file,
+            &format!("{name}_BASE_FEE_UPDATE_FRACTION"),
+            params.base_fee_update_fraction,
+        )?;
+    }
 
     if let Some(premine) = genesis.premine.as_ref() {
         export(
@@ -123,15 +264,132 @@ pub fn write_config(
             ),
         )?;
     }
-    //export(file, "ADDITIONAL_PRELOADED_CONTRACTS", )?;
+
+    // addresses are case-insensitive, so normalize them to let inline entries override files
+    let mut alloc = BTreeMap::new();
+    for path in &genesis.alloc_files {
+        let entries: HashMap<String, AllocEntry> =
+            serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        alloc.extend(entries.into_iter().map(normalize_address));
+    }
+    alloc.extend(genesis.alloc.clone().into_iter().map(normalize_address));
+    if !alloc.is_empty() {
+        export(
+            file,
+            "ADDITIONAL_PRELOADED_CONTRACTS",
+            shell_escape(&serde_json::to_string(&alloc)?),
+        )?;
+    }
+
+    for (key, value) in &genesis.extra {
+        let value = match value {
+            Value::Bool(b) => b.to_string(),
+            Value::Number(n) => n.to_string(),
+            Value::String(s) => s.clone(),
+            _ => return Err(Error::ExpectedOtherType(format!("genesis.extra.{key}"))),
+        };
+        export(file, key, value)?;
+    }
 
     Ok(())
 }
 
+fn normalize_address((address, entry): (String, AllocEntry)) -> (String, AllocEntry) {
+    let address = address.to_lowercase();
+    match address.strip_prefix("0x") {
+        Some(_) => (address, entry),
+        None => (format!("0x{address}"), entry),
+    }
+}
+
+/// Makes sure the generator picks up every blob parameter only fork, and that they happen in
+/// order after Fulu.
+fn validate_blob_schedule(genesis: &Genesis) -> Result<(), Error> {
+    if genesis.blob_schedule.len() > MAX_BLOB_PARAMETER_FORKS {
+        return Err(Error::InvalidBlobSchedule(format!(
+            "the genesis generator only supports {MAX_BLOB_PARAMETER_FORKS} blob parameter only \
+            forks, but {} are configured",
+            genesis.blob_schedule.len()
+        )));
+    }
+    let fulu = genesis.fulu_epoch.map(|time| genesis.epoch_at(time));
+    let mut previous = None;
+    for (idx, params) in genesis.blob_schedule.iter().enumerate() {
+        let epoch = genesis.epoch_at(params.epoch);
+        let name = format!("BPO_{}", idx + 1);
+        match fulu {
+            None => {
+                return Err(Error::InvalidBlobSchedule(format!(
+                    "{name} requires the Fulu fork to be scheduled"
+                )))
+            }
+            Some(fulu) if epoch < fulu => {
+                return Err(Error::InvalidBlobSchedule(format!(
+                    "{name} at epoch {epoch} is before the Fulu fork at epoch {fulu}"
+                )))
+            }
+            _ => {}
+        }
+        if previous.is_some_and(|previous| epoch <= previous) {
+            return Err(Error::InvalidBlobSchedule(format!(
+                "{name} at epoch {epoch} is not after the previous blob parameter only fork"
+            )));
+        }
+        previous = Some(epoch);
+    }
+    Ok(())
+}
+
+/// Resolves the epoch of a fork. Forks that would not happen during the simulation are rejected,
+/// unless `allow_forks_after_stop_time` is set, in which case we only warn.
+fn fork_epoch(
+    genesis: &Genesis,
+    name: &str,
+    time: Option<ForkTime>,
+    default: u64,
+    stop_time: Option<Duration>,
+) -> Result<u64, Error> {
+    let Some(time) = time else {
+        return Ok(default);
+    };
+    let epoch = genesis.epoch_at(time);
+    let start = genesis.epoch_start(epoch);
+    info!(
+        "{name} fork scheduled for epoch {epoch} at {}",
+        format_duration(start)
+    );
+    if let Some(stop_time) = stop_time {
+        if start >= stop_time {
+            let message = format!(
+                "{name} fork at {} is after the end of the simulation at {}",
+                format_duration(start),
+                format_duration(stop_time)
+            );
+            if !genesis.allow_forks_after_stop_time {
+                return Err(Error::ForkAfterStopTime(message));
+            }
+            warn!("{message}");
+        }
+    }
+    Ok(epoch)
+}
+
 fn export<W: Write, V: Display>(file: &mut W, key: &str, value: V) -> std::io::Result<()> {
     writeln!(file, "export {key}=\"{value}\"")
 }
 
+/// Escapes a string so that it can be used within double quotes in a shell script.
+fn shell_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | '$' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
 fn export_optional<W: Write, V: Display>(
     file: &mut W,
     key: &str,
@@ -0,0 +1,287 @@
+//! Grafana dashboards for the metrics captured by the in-simulation Prometheus instances.
+//!
+//! We write a provisioning directory for Grafana, containing a data source per Prometheus
+//! instance and a dashboard based on the labels attached by ethshadow, as well as a
+//! `docker-compose.yaml` serving the recorded data with Prometheus and Grafana.
+
+use crate::export::simulation_end;
+use crate::genesis::SHADOW_START_TIMESTAMP;
+use crate::manifest::{HostRecord, Manifest, MANIFEST_FILE};
+use crate::Error;
+use humantime_serde::re::humantime::format_rfc3339_seconds;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, write, File};
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+pub const GRAFANA_DIR: &str = "grafana";
+pub const COMPOSE_FILE: &str = "docker-compose.yaml";
+const GRAFANA_PORT: u16 = 3000;
+
+#[derive(Serialize)]
+struct DataSources {
+    #[serde(rename = "apiVersion")]
+    api_version: u8,
+    datasources: Vec<DataSource>,
+}
+
+#[derive(Serialize)]
+struct DataSource {
+    name: String,
+    #[serde(rename = "type")]
+    source_type: &'static str,
+    access: &'static str,
+    url: String,
+    #[serde(rename = "isDefault")]
+    is_default: bool,
+}
+
+#[derive(Serialize)]
+struct DashboardProviders {
+    #[serde(rename = "apiVersion")]
+    api_version: u8,
+    providers: Vec<DashboardProvider>,
+}
+
+#[derive(Serialize)]
+struct DashboardProvider {
+    name: &'static str,
+    folder: &'static str,
+    #[serde(rename = "type")]
+    provider_type: &'static str,
+    options: BTreeMap<&'static str, &'static str>,
+}
+
+#[derive(Serialize)]
+struct Compose {
+    services: BTreeMap<String, Service>,
+}
+
+#[derive(Serialize)]
+struct Service {
+    image: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<&'static str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    command: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    ports: Vec<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    environment: BTreeMap<&'static str, &'static str>,
+    volumes: Vec<String>,
+}
+
+/// Writes dashboards, data sources and a compose file for the simulation in `dir` to `output`.
+pub fn generate_grafana(dir: &Path, output: &Path) -> Result<(), Error> {
+    let manifest: Manifest = serde_json::from_reader(File::open(dir.join(MANIFEST_FILE))?)?;
+    let instances: Vec<&HostRecord> = manifest
+        .hosts
+        .iter()
+        .filter(|host| {
+            host.clients
+                .iter()
+                .any(|client| client.client_type == "prometheus")
+        })
+        .collect();
+    if instances.is_empty() {
+        return Err(Error::NoPrometheus);
+    }
+    let end = simulation_end(dir)?;
+
+    let datasources_dir = output.join("provisioning").join("datasources");
+    let providers_dir = output.join("provisioning").join("dashboards");
+    let dashboards_dir = output.join("dashboards");
+    create_dir_all(&datasources_dir)?;
+    create_dir_all(&providers_dir)?;
+    create_dir_all(&dashboards_dir)?;
+
+    let datasources = DataSources {
+        api_version: 1,
+        datasources: instances
+            .iter()
+            .enumerate()
+            .map(|(idx, host)| DataSource {
+                name: host.name.clone(),
+                source_type: "prometheus",
+                access: "proxy",
+                url: format!("http://{}:9090", service_name(host)),
+                is_default: idx == 0,
+            })
+            .collect(),
+    };
+    serde_yaml::to_writer(
+        File::create(datasources_dir.join("ethshadow.yaml"))?,
+        &datasources,
+    )?;
+
+    let providers = DashboardProviders {
+        api_version: 1,
+        providers: vec![DashboardProvider {
+            name: "ethshadow",
+            folder: "ethshadow",
+            provider_type: "file",
+            options: BTreeMap::from([("path", "/var/lib/grafana/dashboards")]),
+        }],
+    };
+    serde_yaml::to_writer(
+        File::create(providers_dir.join("ethshadow.yaml"))?,
+        &providers,
+    )?;
+
+    write(
+        dashboards_dir.join("ethshadow.json"),
+        serde_json::to_string_pretty(&dashboard(end))?,
+    )?;
+
+    let mut services = BTreeMap::new();
+    for host in &instances {
+        let tsdb = host.directory.join("prometheus").canonicalize()?;
+        services.insert(
+            service_name(host),
+            Service {
+                image: "prom/prometheus",
+                // the TSDB was written by the user running the simulation
+                user: Some("root"),
+                command: vec![
+                    "--storage.tsdb.path=/prometheus".to_string(),
+                    "--storage.tsdb.retention.time=100y".to_string(),
+                    "--config.file=/dev/null".to_string(),
+                ],
+                ports: vec![],
+                environment: BTreeMap::new(),
+                volumes: vec![format!("{}:/prometheus", tsdb.display())],
+            },
+        );
+    }
+    let output = output.canonicalize()?;
+    services.insert(
+        "grafana".to_string(),
+        Service {
+            image: "grafana/grafana",
+            user: None,
+            command: vec![],
+            ports: vec![format!("{GRAFANA_PORT}:{GRAFANA_PORT}")],
+            environment: BTreeMap::from([
+                ("GF_AUTH_ANONYMOUS_ENABLED", "true"),
+                ("GF_AUTH_ANONYMOUS_ORG_ROLE", "Admin"),
+            ]),
+            volumes: vec![
+                format!(
+                    "{}:/etc/grafana/provisioning",
+                    output.join("provisioning").display()
+                ),
+                format!(
+                    "{}:/var/lib/grafana/dashboards",
+                    output.join("dashboards").display()
+                ),
+            ],
+        },
+    );
+    serde_yaml::to_writer(
+        File::create(output.join(COMPOSE_FILE))?,
+        &Compose { services },
+    )?;
+    Ok(())
+}
+
+fn service_name(host: &HostRecord) -> String {
+    format!("prometheus-{}", host.name)
+}
+
+/// The dashboard covers the whole simulation, which always starts at 2000-01-01.
+fn dashboard(end: Duration) -> Value {
+    let start = UNIX_EPOCH + Duration::from_secs(SHADOW_START_TIMESTAMP);
+    let filter = r#"location=~"$location", reliability=~"$reliability""#;
+    let panels = [
+        (
+            "Head slot",
+            format!("max by (host) (beacon_head_slot{{{filter}}})"),
+            "{{host}}",
+            "none",
+        ),
+        (
+            "Finalized epoch",
+            format!("min by (location, reliability) (beacon_finalized_epoch{{{filter}}})"),
+            "{{location}} / {{reliability}}",
+            "none",
+        ),
+        (
+            "Peers",
+            format!("avg by (location, reliability) (libp2p_peers{{{filter}}})"),
+            "{{location}} / {{reliability}}",
+            "none",
+        ),
+        (
+            "Block gossip delay (p50)",
+            gossip_delay_quantile(0.5, filter),
+            "{{location}} / {{reliability}}",
+            "s",
+        ),
+        (
+            "Block gossip delay (p95)",
+            gossip_delay_quantile(0.95, filter),
+            "{{location}} / {{reliability}}",
+            "s",
+        ),
+        (
+            "Execution head block",
+            format!("max by (host) (chain_head_block{{{filter}}})"),
+            "{{host}}",
+            "none",
+        ),
+    ];
+    let panels: Vec<Value> = panels
+        .into_iter()
+        .enumerate()
+        .map(|(idx, (title, expr, legend, unit))| {
+            json!({
+                "id": idx + 1,
+                "type": "timeseries",
+                "title": title,
+                "datasource": { "type": "prometheus", "uid": "${datasource}" },
+                "gridPos": { "h": 8, "w": 12, "x": (idx % 2) * 12, "y": (idx / 2) * 8 },
+                "fieldConfig": { "defaults": { "unit": unit }, "overrides": [] },
+                "targets": [{ "refId": "A", "expr": expr, "legendFormat": legend }],
+            })
+        })
+        .collect();
+    let label_variable = |name: &str| {
+        json!({
+            "name": name,
+            "type": "query",
+            "datasource": { "type": "prometheus", "uid": "${datasource}" },
+            "query": format!("label_values({name})"),
+            "multi": true,
+            "includeAll": true,
+            "allValue": ".*",
+            "current": { "text": "All", "value": "$__all" },
+            "refresh": 1,
+        })
+    };
+    json!({
+        "title": "ethshadow",
+        "uid": "ethshadow",
+        "schemaVersion": 39,
+        "time": {
+            "from": format_rfc3339_seconds(start).to_string(),
+            "to": format_rfc3339_seconds(start + end).to_string(),
+        },
+        "templating": {
+            "list": [
+                { "name": "datasource", "type": "datasource", "query": "prometheus" },
+                label_variable("location"),
+                label_variable("reliability"),
+            ],
+        },
+        "panels": panels,
+    })
+}
+
+fn gossip_delay_quantile(quantile: f64, filter: &str) -> String {
+    format!(
+        "histogram_quantile({quantile}, sum by (le, location, reliability) \
+        (rate(beacon_block_gossip_slot_start_delay_time_bucket{{{filter}}}[$__rate_interval])))"
+    )
+}
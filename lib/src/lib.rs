@@ -1,5 +1,6 @@
-use crate::config::ethshadow::DEFAULT_GENESIS_GEN_IMAGE;
+use crate::config::ethshadow::{IpVersion, DEFAULT_GENESIS_GEN_IMAGE};
 use crate::config::FullConfig;
+use crate::manifest::MANIFEST_FILE;
 use crate::network_graph::{generate_network_graph, GeneratedNetworkGraph};
 use crate::node::NodeManager;
 use crate::validators::ValidatorManager;
@@ -11,14 +12,24 @@ use std::fs::{create_dir, File};
 use std::io::ErrorKind;
 use std::path::Path;
 use std::process::Command;
+use std::time::Duration;
 
+pub mod addressing;
+pub mod analysis;
 mod clients;
 pub mod config;
 mod error;
+pub mod export;
 pub mod genesis;
 mod gml;
+pub mod grafana;
+pub mod manifest;
 pub mod network_graph;
 pub mod node;
+pub mod peering;
+pub mod remote_write;
+pub mod run;
+pub mod sweep;
 mod utils;
 pub mod validators;
 
@@ -30,16 +41,27 @@ type CowStr = Cow<'static, str>;
 pub struct ShadowInvocation {
     command: Command,
     args: Vec<OsString>,
+    stop_time: Option<Duration>,
 }
 
 impl ShadowInvocation {
-    fn new<S: AsRef<OsStr>>(executable: S, args: Vec<OsString>) -> ShadowInvocation {
+    pub fn new<S: AsRef<OsStr>>(
+        executable: S,
+        args: Vec<OsString>,
+        stop_time: Option<Duration>,
+    ) -> ShadowInvocation {
         ShadowInvocation {
             command: Command::new(executable),
             args,
+            stop_time,
         }
     }
 
+    /// The configured `general.stop_time` of the simulation, if any.
+    pub fn stop_time(&self) -> Option<Duration> {
+        self.stop_time
+    }
+
     pub fn with_user_args<I, S>(&mut self, user_args: I)
     where
         I: IntoIterator<Item = S>,
@@ -48,6 +70,14 @@ impl ShadowInvocation {
         self.command.args(user_args);
     }
 
+    /// The full command line used to invoke Shadow, including the executable.
+    pub fn command_line(&self) -> Vec<OsString> {
+        let mut command_line = vec![self.command.get_program().to_os_string()];
+        command_line.extend(self.command.get_args().map(OsStr::to_os_string));
+        command_line.extend(self.args.iter().cloned());
+        command_line
+    }
+
     pub fn command(mut self) -> Command {
         self.command.args(self.args);
         self.command
@@ -65,8 +95,41 @@ pub fn generate<T: TryInto<FullConfig, Error = Error>>(
         mut ethshadow_config,
         mut shadow_config,
     } = config.try_into()?;
+    shadow_config.validate()?;
+    if ethshadow_config.addressing.ip_version == IpVersion::Ipv6 {
+        return Err(Error::Ipv6OnlyUnsupported);
+    }
     ethshadow_config.add_default_builtins();
-    shadow_config.apply_defaults(ethshadow_config.minimum_latency())?;
+    shadow_config.apply_defaults()?;
+
+    debug!("Generating network graph");
+    let GeneratedNetworkGraph {
+        gml,
+        mut network_graph,
+        minimum_latency,
+    } = generate_network_graph(&ethshadow_config)?;
+    shadow_config.set_network(gml, false)?;
+    shadow_config.apply_runahead(minimum_latency, ethshadow_config.allow_large_runahead)?;
+
+    // postprocessing given shadow config values: overwrite string network ids
+    for host in shadow_config.hosts_mut()? {
+        let mapping = host?;
+        if mapping.get("network_node_id").is_some() {
+            return Err(Error::InvalidShadowHost);
+        }
+        let Value::String(location) = mapping.remove("location").ok_or(Error::InvalidShadowHost)?
+        else {
+            return Err(Error::ExpectedOtherType("location".to_string()));
+        };
+        let Value::String(reliability) = mapping
+            .remove("reliability")
+            .ok_or(Error::InvalidShadowHost)?
+        else {
+            return Err(Error::ExpectedOtherType("reliability".to_string()));
+        };
+        let node = network_graph.assign_network_node(&location, &reliability)?;
+        mapping.insert("network_node_id".into(), node.id().into());
+    }
 
     debug!("Creating output directory");
     if let Err(e) = create_dir(output_path) {
@@ -90,6 +153,7 @@ pub fn generate<T: TryInto<FullConfig, Error = Error>>(
     genesis::write_config(
         &ethshadow_config.genesis,
         validators.total_count(),
+        shadow_config.stop_time()?,
         output_path.clone(),
     )?;
     genesis::generate(
@@ -101,33 +165,6 @@ pub fn generate<T: TryInto<FullConfig, Error = Error>>(
         &output_path,
     )?;
 
-    debug!("Generating network graph");
-    let GeneratedNetworkGraph {
-        gml,
-        mut network_graph,
-    } = generate_network_graph(&ethshadow_config)?;
-    shadow_config.set_network(gml, false)?;
-
-    // postprocessing given shadow config values: overwrite string network ids
-    for host in shadow_config.hosts_mut()? {
-        let mapping = host?;
-        if mapping.get("network_node_id").is_some() {
-            return Err(Error::InvalidShadowHost);
-        }
-        let Value::String(location) = mapping.remove("location").ok_or(Error::InvalidShadowHost)?
-        else {
-            return Err(Error::ExpectedOtherType("location".to_string()));
-        };
-        let Value::String(reliability) = mapping
-            .remove("reliability")
-            .ok_or(Error::InvalidShadowHost)?
-        else {
-            return Err(Error::ExpectedOtherType("reliability".to_string()));
-        };
-        let node = network_graph.assign_network_node(&location, &reliability)?;
-        mapping.insert("network_node_id".into(), node.id().into());
-    }
-
     info!("Generating nodes");
     let mut node_manager = NodeManager::new(
         output_path.clone(),
@@ -135,9 +172,15 @@ pub fn generate<T: TryInto<FullConfig, Error = Error>>(
         &mut shadow_config,
         network_graph,
         validators,
+        &ethshadow_config,
     );
     node_manager.generate_nodes()?;
 
+    info!("Writing manifest");
+    output_path.push(MANIFEST_FILE);
+    serde_json::to_writer_pretty(File::create_new(&output_path)?, node_manager.manifest())?;
+    output_path.pop();
+
     info!("Writing finished configuration");
     output_path.push("shadow.yaml");
     serde_yaml::to_writer(File::create_new(&output_path)?, &shadow_config.0)?;
@@ -148,5 +191,6 @@ pub fn generate<T: TryInto<FullConfig, Error = Error>>(
     Ok(ShadowInvocation::new(
         ethshadow_config.shadow_path.as_deref().unwrap_or("shadow"),
         vec!["-d".into(), output_path.into_os_string(), config_path],
+        shadow_config.stop_time()?,
     ))
 }
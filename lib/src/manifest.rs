@@ -0,0 +1,56 @@
+//! A machine-readable description of the generated simulation, written as `manifest.json` next to
+//! `shadow.yaml`, so that downstream tooling does not have to reverse-engineer the data directory.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::PathBuf;
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Manifest {
+    pub hosts: Vec<HostRecord>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HostRecord {
+    pub name: String,
+    pub ip: Ipv4Addr,
+    /// Only present with dual stack addressing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipv6: Option<Ipv6Addr>,
+    pub network_node_id: u64,
+    pub location: String,
+    pub reliability: String,
+    pub tag: Option<String>,
+    /// Directory containing the data of the clients
+    pub directory: PathBuf,
+    /// Directory where Shadow puts the output of the processes
+    pub shadow_directory: PathBuf,
+    pub clients: Vec<ClientRecord>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClientRecord {
+    /// The id of the client in the `clients` section of the config
+    pub id: String,
+    #[serde(rename = "type")]
+    pub client_type: String,
+    pub ports: BTreeMap<String, u16>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub validators: Vec<ValidatorRecord>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub enode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub enr: Option<String>,
+    /// The libp2p peer id of consensus clients
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub peer_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ValidatorRecord {
+    pub index: Option<usize>,
+    pub pubkey: String,
+}
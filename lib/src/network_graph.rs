@@ -3,13 +3,18 @@ use crate::config::EthShadowConfig;
 use crate::gml::{Gml, NetworkNode};
 use crate::Error;
 use std::collections::BTreeMap;
+use std::time::Duration;
 
 pub struct GeneratedNetworkGraph<'a> {
     pub gml: String,
     pub network_graph: Box<dyn NetworkGraph + 'a>,
+    /// The smallest latency of all edges in the generated graph
+    pub minimum_latency: Duration,
 }
 
-pub fn generate_network_graph(config: &EthShadowConfig) -> Result<GeneratedNetworkGraph, Error> {
+pub fn generate_network_graph(
+    config: &EthShadowConfig,
+) -> Result<GeneratedNetworkGraph<'_>, Error> {
     match &config.topology {
         Topology::Simple => SimpleNetworkGraph::generate(config),
         Topology::Clustered(_clusters) => todo!(),
@@ -29,12 +34,13 @@ pub struct SimpleNetworkGraph<'a> {
 }
 
 impl SimpleNetworkGraph<'_> {
-    pub fn generate(config: &EthShadowConfig) -> Result<GeneratedNetworkGraph, Error> {
+    pub fn generate(config: &EthShadowConfig) -> Result<GeneratedNetworkGraph<'_>, Error> {
         let mut network_graph = Box::new(SimpleNetworkGraph {
             nodes: BTreeMap::new(),
         });
         let mut gml = String::new();
         let mut gml_builder = Gml::new(&mut gml, true)?;
+        let mut minimum_latency = Duration::MAX;
         for location_name in config.locations.keys() {
             for (reliability_name, reliability) in &config.reliabilities {
                 let node = gml_builder.add_node(
@@ -80,6 +86,7 @@ impl SimpleNetworkGraph<'_> {
                             + dest_reliability.added_latency.into_inner();
                         packet_loss +=
                             src_reliability.added_packet_loss + dest_reliability.added_packet_loss;
+                        minimum_latency = minimum_latency.min(latency);
                         gml_builder.add_edge(
                             src_node,
                             dest_node,
@@ -95,7 +102,11 @@ impl SimpleNetworkGraph<'_> {
             }
         }
         gml_builder.finish()?;
-        Ok(GeneratedNetworkGraph { gml, network_graph })
+        Ok(GeneratedNetworkGraph {
+            gml,
+            network_graph,
+            minimum_latency,
+        })
     }
 
     fn get_network_node(&self, location: &str, reliability: &str) -> Result<NetworkNode, Error> {
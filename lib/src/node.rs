@@ -1,15 +1,21 @@
+use crate::addressing::IpAllocator;
 use crate::clients::Client;
-use crate::config::ethshadow::Node;
+use crate::config::ethshadow::{
+    BootnodeStrategy, EthShadowConfig, Genesis, IpVersion, Node, StaticPeering,
+};
 use crate::config::shadow::Host;
 use crate::config::ShadowConfig;
+use crate::manifest::{ClientRecord, HostRecord, Manifest, ValidatorRecord};
 use crate::network_graph::NetworkGraph;
+use crate::peering::{build_graph, NodeKey};
+use crate::utils::stable_hash;
 use crate::validators::ValidatorManager;
 use crate::Error;
 use rand::prelude::*;
-use std::collections::{HashMap, HashSet};
 use std::fs::{create_dir, File};
-use std::net::Ipv4Addr;
+use std::net::{Ipv4Addr, Ipv6Addr};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 pub struct NodeManager<'c, 'n> {
     ctx: SimulationContext<'n>,
@@ -18,14 +24,29 @@ pub struct NodeManager<'c, 'n> {
     shadow_config: &'c mut ShadowConfig,
     network_nodes: Box<dyn NetworkGraph + 'n>,
     validator_manager: ValidatorManager,
-    used_ips: HashSet<Ipv4Addr>,
+    ips: IpAllocator,
+    manifest: Manifest,
 }
 
+/// A host whose clients are registered in the context, but not yet added to it.
+struct PlannedNode<'n> {
+    name: String,
+    info: NodeInfo<'n>,
+    host: Host,
+    record: HostRecord,
+    clients: &'n [(&'n str, &'n dyn Client)],
+}
+
+#[derive(Clone)]
 pub struct NodeInfo<'a> {
+    name: String,
+    client_id: &'a str,
     ip: Ipv4Addr,
+    ipv6: Option<Ipv6Addr>,
     dir: PathBuf,
     location: &'a str,
     reliability: &'a str,
+    tag: Option<&'a str>,
 }
 
 impl<'c, 'n> NodeManager<'c, 'n> {
@@ -35,11 +56,12 @@ impl<'c, 'n> NodeManager<'c, 'n> {
         shadow_config: &'c mut ShadowConfig,
         network_nodes: Box<dyn NetworkGraph + 'n>,
         validator_manager: ValidatorManager,
+        config: &'n EthShadowConfig,
     ) -> Self {
         let mut num_el_clients = 0;
         let mut num_cl_clients = 0;
         for node in nodes {
-            for client in &node.clients {
+            for (_, client) in &node.clients {
                 if client.is_el_client() {
                     num_el_clients += node.count;
                 }
@@ -48,11 +70,12 @@ impl<'c, 'n> NodeManager<'c, 'n> {
                 }
             }
         }
-        let rng = StdRng::seed_from_u64(shadow_config.seed());
+        let seed = shadow_config.seed();
         let ctx = SimulationContext::new(
-            rng,
+            seed,
             base_dir.join("metadata"),
             base_dir.join("jwt/jwtsecret"),
+            config,
             num_el_clients,
             num_cl_clients,
         );
@@ -63,19 +86,23 @@ impl<'c, 'n> NodeManager<'c, 'n> {
             shadow_config,
             network_nodes,
             validator_manager,
-            used_ips: HashSet::new(),
+            ips: IpAllocator::new(config.addressing.allocation),
+            manifest: Manifest::default(),
         }
     }
 
-    fn gen_node(
-        &mut self,
-        tag: &str,
-        clients: &[&dyn Client],
-        location: &'n str,
-        reliability: &'n str,
-    ) -> Result<(), Error> {
-        let idx = self.used_ips.len();
-        let name = format!("node{idx}{tag}");
+    /// First phase: allocate the host and let each client register itself in the context.
+    fn plan_node(&mut self, node: &'n Node<'n>) -> Result<PlannedNode<'n>, Error> {
+        let Node {
+            tag,
+            ref clients,
+            location,
+            reliability,
+            subnet,
+            ..
+        } = *node;
+        let idx = self.ips.allocated();
+        let name = format!("node{idx}{}", tag.unwrap_or(""));
 
         let dir = self.base_dir.join(&name);
         create_dir(&dir)?;
@@ -83,19 +110,24 @@ impl<'c, 'n> NodeManager<'c, 'n> {
         File::create_new(dir.join(location))?;
         File::create_new(dir.join(reliability))?;
 
-        let mut ip = random_ip(self.ctx.rng());
-        while !self.used_ips.insert(ip) {
-            ip = random_ip(self.ctx.rng());
-        }
+        let ip = self.ips.allocate(location, subnet, self.ctx.rng())?;
+        let ipv6 = match self.ctx.config().addressing.ip_version {
+            IpVersion::Ipv4 => None,
+            IpVersion::DualStack | IpVersion::Ipv6 => Some(self.ips.allocate_ipv6(self.ctx.rng())),
+        };
 
-        let node = NodeInfo {
+        let info = NodeInfo {
+            name: name.clone(),
+            client_id: "",
             ip,
+            ipv6,
             dir,
             location,
             reliability,
+            tag,
         };
 
-        let mut host = Host {
+        let host = Host {
             ip_addr: ip.to_string(),
             network_node_id: self
                 .network_nodes
@@ -103,38 +135,161 @@ impl<'c, 'n> NodeManager<'c, 'n> {
                 .id(),
             processes: vec![],
         };
+        let mut record = HostRecord {
+            name: name.clone(),
+            ip,
+            ipv6,
+            network_node_id: host.network_node_id,
+            location: location.to_string(),
+            reliability: reliability.to_string(),
+            tag: tag.map(str::to_string),
+            directory: info.dir.clone(),
+            shadow_directory: self.base_dir.join("shadow").join("hosts").join(&name),
+            clients: vec![],
+        };
 
-        for &client in clients {
+        for &(id, client) in clients {
+            let info = info.for_client(id);
+            // clients register their identity in the context, so any new entry is theirs
+            let enodes = self.ctx.el_bootnodes().len();
+            let enrs = self.ctx.cl_bootnodes().len();
+            let sybil_enrs = self.ctx.sybil_enrs.len();
+            let el_identities = self.ctx.el_identities().len();
+            let cl_identities = self.ctx.cl_identities().len();
+            client.register(&info, &mut self.ctx)?;
+
+            if let Some((port, metrics_path)) = client.metrics_endpoint() {
+                self.ctx.add_monitoring_endpoint(MonitoringEndpoint {
+                    client_type: client.client_type(),
+                    client_id: id.to_string(),
+                    tag: tag.map(str::to_string),
+                    host: name.clone(),
+                    location: location.to_string(),
+                    reliability: reliability.to_string(),
+                    target: format!("{ip}:{port}"),
+                    metrics_path,
+                });
+            }
+
+            record.clients.push(ClientRecord {
+                id: id.to_string(),
+                client_type: client.client_type().to_string(),
+                ports: client
+                    .ports()
+                    .into_iter()
+                    .map(|(purpose, port)| (purpose.to_string(), port))
+                    .collect(),
+                validators: vec![],
+                enode: self
+                    .ctx
+                    .el_bootnodes()
+                    .get(enodes)
+                    .map(|bootnode| bootnode.identity.clone())
+                    .or_else(|| {
+                        let identity = self.ctx.el_identities().get(el_identities)?;
+                        Some(identity.id.clone())
+                    }),
+                enr: self
+                    .ctx
+                    .cl_bootnodes()
+                    .get(enrs)
+                    .map(|bootnode| bootnode.identity.clone())
+                    .or_else(|| Some(self.ctx.sybil_enrs.get(sybil_enrs)?.enr.clone())),
+                peer_id: self
+                    .ctx
+                    .cl_identities()
+                    .get(cl_identities)
+                    .map(|identity| identity.id.clone()),
+            });
+        }
+
+        Ok(PlannedNode {
+            name,
+            info,
+            host,
+            record,
+            clients,
+        })
+    }
+
+    /// Second phase: create the processes, now that the whole network is known.
+    fn gen_node(&mut self, planned: PlannedNode<'n>) -> Result<(), Error> {
+        let PlannedNode {
+            name,
+            info,
+            mut host,
+            mut record,
+            clients,
+        } = planned;
+
+        for (&(id, client), client_record) in clients.iter().zip(&mut record.clients) {
+            let info = info.for_client(id);
             let validators = self.validator_manager.assign(client);
-            let process = client.add_to_node(&node, &mut self.ctx, validators)?;
+            let process = client.add_to_node(&info, &mut self.ctx, validators)?;
             host.processes.push(process);
+
+            client_record.validators = validators
+                .iter()
+                .map(|validator| ValidatorRecord {
+                    index: validator.index(),
+                    pubkey: validator.pubkey(),
+                })
+                .collect();
         }
 
         self.shadow_config.add_host(name, &host)?;
+        self.manifest.hosts.push(record);
 
         Ok(())
     }
 
     pub fn generate_nodes(&mut self) -> Result<(), Error> {
+        let mut planned = vec![];
         for node in self.nodes {
             for _ in 0..node.count {
-                self.gen_node(
-                    node.tag.unwrap_or(""),
-                    &node.clients,
-                    node.location,
-                    node.reliability,
-                )?;
+                planned.push(self.plan_node(node)?);
             }
         }
+        self.ctx.build_peer_graphs();
+        for node in planned {
+            self.gen_node(node)?;
+        }
         Ok(())
     }
+
+    /// Description of all generated nodes.
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
 }
 
 impl<'a> NodeInfo<'a> {
+    /// The same node, as seen by the client with the given id.
+    fn for_client(&self, client_id: &'a str) -> Self {
+        NodeInfo {
+            client_id,
+            ..self.clone()
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The name in the `clients` section of the client being registered or added to the node.
+    pub fn client_id(&self) -> &'a str {
+        self.client_id
+    }
+
     pub fn ip(&self) -> Ipv4Addr {
         self.ip
     }
 
+    /// The IPv6 address of the node, if dual stack addressing is enabled.
+    pub fn ipv6(&self) -> Option<Ipv6Addr> {
+        self.ipv6
+    }
+
     pub fn dir(&self) -> &Path {
         self.dir.as_path()
     }
@@ -146,52 +301,105 @@ impl<'a> NodeInfo<'a> {
     pub fn reliability(&self) -> &'a str {
         self.reliability
     }
+
+    pub fn tag(&self) -> Option<&'a str> {
+        self.tag
+    }
+}
+
+/// A Prometheus metrics endpoint of a client, along with information to label its metrics.
+#[derive(Debug, Clone)]
+pub struct MonitoringEndpoint {
+    pub client_type: &'static str,
+    pub client_id: String,
+    pub tag: Option<String>,
+    pub host: String,
+    pub location: String,
+    pub reliability: String,
+    /// `ip:port`
+    pub target: String,
+    pub metrics_path: &'static str,
 }
 
-// we want to avoid hitting a reserved IP range, as that might invoke special behavior in clients.
-// we also want to distribute the addresses as wide as possible, as e.g. `bootnode` has buckets
-// for IP ranges. As there are a lot of reserved IP ranges, we don't bother having each possible
-// IP, and choose 11-99 for the first octet. The last octet will be chosen within 1-254, as the
-// first and last IP of a subnet is special.
-fn random_ip<R: Rng>(rng: &mut R) -> Ipv4Addr {
-    Ipv4Addr::new(
-        rng.gen_range(11..=99),
-        rng.gen(),
-        rng.gen(),
-        rng.gen_range(1..=254),
-    )
+/// The enode or ENR of a bootnode, along with its location.
+#[derive(Debug, Clone)]
+pub struct Bootnode<'a> {
+    pub identity: String,
+    pub location: &'a str,
+}
+
+/// The p2p identity of an EL or CL client, generated by ethshadow.
+#[derive(Debug, Clone)]
+pub struct PeerIdentity<'a> {
+    pub host: String,
+    /// The name of the client in the `clients` section
+    pub client_id: String,
+    pub ip: Ipv4Addr,
+    pub location: &'a str,
+    pub tag: Option<&'a str>,
+    /// The address to dial, i.e. an enode or a multiaddr
+    pub address: String,
+    /// The identity to trust, i.e. the enode or the peer id
+    pub id: String,
+}
+
+/// The ENR of a sybil node, handed to the nodes it attacks instead of the regular bootnodes.
+#[derive(Debug, Clone)]
+pub struct SybilEnr {
+    /// Tag of the attacked nodes
+    pub victims: String,
+    pub enr: String,
+    /// Whether the victims should receive no regular bootnodes
+    pub exclusive: bool,
 }
 
 pub struct SimulationContext<'a> {
     rng: StdRng,
+    seed: u64,
     metadata_path: PathBuf,
     jwt_path: PathBuf,
-    el_bootnode_enodes: Vec<String>,
-    cl_bootnode_enrs: Vec<String>,
+    config: &'a EthShadowConfig,
+    el_bootnodes: Vec<Bootnode<'a>>,
+    cl_bootnodes: Vec<Bootnode<'a>>,
+    sybil_enrs: Vec<SybilEnr>,
     el_http_endpoints: Vec<String>,
     cl_http_endpoints: Vec<String>,
-    cl_monitoring_endpoints: HashMap<(&'a str, &'a str), Vec<String>>,
+    monitoring_endpoints: Vec<MonitoringEndpoint>,
+    prometheus_shards: Vec<String>,
+    el_identities: Vec<PeerIdentity<'a>>,
+    cl_identities: Vec<PeerIdentity<'a>>,
+    el_peer_graph: Vec<Vec<usize>>,
+    cl_peer_graph: Vec<Vec<usize>>,
     num_el_clients: usize,
     num_cl_clients: usize,
 }
 
 impl<'a> SimulationContext<'a> {
     pub fn new(
-        rng: StdRng,
+        seed: u64,
         metadata_path: PathBuf,
         jwt_path: PathBuf,
+        config: &'a EthShadowConfig,
         num_el_clients: usize,
         num_cl_clients: usize,
     ) -> Self {
         Self {
-            rng,
+            rng: StdRng::seed_from_u64(seed),
+            seed,
             metadata_path,
             jwt_path,
-            el_bootnode_enodes: vec![],
-            cl_bootnode_enrs: vec![],
+            config,
+            el_bootnodes: vec![],
+            cl_bootnodes: vec![],
+            sybil_enrs: vec![],
             el_http_endpoints: Vec::with_capacity(num_el_clients),
             cl_http_endpoints: Vec::with_capacity(num_cl_clients),
-            cl_monitoring_endpoints: HashMap::with_capacity(num_cl_clients),
+            monitoring_endpoints: vec![],
+            prometheus_shards: vec![],
+            el_identities: vec![],
+            cl_identities: vec![],
+            el_peer_graph: vec![],
+            cl_peer_graph: vec![],
             num_el_clients,
             num_cl_clients,
         }
@@ -201,6 +409,13 @@ impl<'a> SimulationContext<'a> {
         &mut self.rng
     }
 
+    /// A p2p key for a client on the given node. It is derived from the seed, the name of the node
+    /// and the client kind only, so that it does not change if other nodes are added.
+    pub fn node_key(&self, node: &NodeInfo, kind: &str) -> NodeKey {
+        let seed = self.seed ^ stable_hash(&format!("{}/{kind}", node.name));
+        NodeKey::random(&mut StdRng::seed_from_u64(seed))
+    }
+
     pub fn metadata_path(&self) -> &Path {
         self.metadata_path.as_path()
     }
@@ -209,12 +424,82 @@ impl<'a> SimulationContext<'a> {
         self.jwt_path.as_path()
     }
 
-    pub fn el_bootnode_enodes(&self) -> &[String] {
-        self.el_bootnode_enodes.as_slice()
+    pub fn genesis(&self) -> &'a Genesis {
+        &self.config.genesis
+    }
+
+    pub fn config(&self) -> &'a EthShadowConfig {
+        self.config
+    }
+
+    pub fn el_bootnodes(&self) -> &[Bootnode<'a>] {
+        self.el_bootnodes.as_slice()
     }
 
-    pub fn cl_bootnode_enrs(&self) -> &[String] {
-        self.cl_bootnode_enrs.as_slice()
+    pub fn cl_bootnodes(&self) -> &[Bootnode<'a>] {
+        self.cl_bootnodes.as_slice()
+    }
+
+    /// The enodes of the EL bootnodes the given node should connect to.
+    pub fn el_bootnode_enodes_for(&mut self, node: &NodeInfo) -> Vec<String> {
+        self.select_bootnodes(node, false)
+    }
+
+    /// The ENRs of the CL bootnodes the given node should connect to. Nodes attacked by sybils
+    /// receive the ENRs of the sybils first.
+    pub fn cl_bootnode_enrs_for(&mut self, node: &NodeInfo) -> Vec<String> {
+        let sybils: Vec<&SybilEnr> = self
+            .sybil_enrs
+            .iter()
+            .filter(|sybil| node.tag == Some(sybil.victims.as_str()))
+            .collect();
+        let exclusive = sybils.iter().any(|sybil| sybil.exclusive);
+        let mut enrs: Vec<String> = sybils.into_iter().map(|sybil| sybil.enr.clone()).collect();
+        if !exclusive {
+            enrs.extend(self.select_bootnodes(node, true));
+        }
+        enrs
+    }
+
+    pub fn add_sybil_enr(&mut self, sybil: SybilEnr) {
+        self.sybil_enrs.push(sybil);
+    }
+
+    fn select_bootnodes(&mut self, node: &NodeInfo, cl: bool) -> Vec<String> {
+        let bootnodes = if cl {
+            &self.cl_bootnodes
+        } else {
+            &self.el_bootnodes
+        };
+        let config = &self.config.bootnodes;
+        let selected: Vec<&Bootnode> = match config.strategy {
+            BootnodeStrategy::All => bootnodes.iter().collect(),
+            BootnodeStrategy::NearestLocation => {
+                let latency = |bootnode: &Bootnode| self.latency(node.location, bootnode.location);
+                match bootnodes.iter().filter_map(latency).min() {
+                    Some(nearest) => bootnodes
+                        .iter()
+                        .filter(|bootnode| latency(bootnode) == Some(nearest))
+                        .collect(),
+                    // no latencies known, so all are equally near
+                    None => bootnodes.iter().collect(),
+                }
+            }
+            BootnodeStrategy::Random => bootnodes
+                .choose_multiple(&mut self.rng, config.per_client)
+                .collect(),
+        };
+        selected
+            .into_iter()
+            .map(|bootnode| bootnode.identity.clone())
+            .collect()
+    }
+
+    /// The configured latency between two locations, in either direction.
+    fn latency(&self, from: &str, to: &str) -> Option<Duration> {
+        let locations = &self.config.locations;
+        let get = |a: &str, b: &str| Some(**locations.get(a)?.latency_to.get(b)?);
+        get(from, to).or_else(|| get(to, from))
     }
 
     pub fn el_http_endpoints(&self) -> &[String] {
@@ -225,16 +510,27 @@ impl<'a> SimulationContext<'a> {
         self.cl_http_endpoints.as_slice()
     }
 
-    pub fn cl_monitoring_endpoints(&self) -> &HashMap<(&str, &str), Vec<String>> {
-        &self.cl_monitoring_endpoints
+    pub fn monitoring_endpoints(&self) -> &[MonitoringEndpoint] {
+        self.monitoring_endpoints.as_slice()
+    }
+
+    /// `ip:port` of each Prometheus instance scraping a shard of the monitoring endpoints
+    pub fn prometheus_shards(&self) -> &[String] {
+        self.prometheus_shards.as_slice()
     }
 
-    pub fn add_el_bootnode_enode(&mut self, enode: String) {
-        self.el_bootnode_enodes.push(enode);
+    pub fn add_el_bootnode_enode(&mut self, enode: String, location: &'a str) {
+        self.el_bootnodes.push(Bootnode {
+            identity: enode,
+            location,
+        });
     }
 
-    pub fn add_cl_bootnode_enr(&mut self, enr: String) {
-        self.cl_bootnode_enrs.push(enr);
+    pub fn add_cl_bootnode_enr(&mut self, enr: String, location: &'a str) {
+        self.cl_bootnodes.push(Bootnode {
+            identity: enr,
+            location,
+        });
     }
 
     pub fn add_el_http_endpoint(&mut self, endpoint: String) {
@@ -245,16 +541,81 @@ impl<'a> SimulationContext<'a> {
         self.cl_http_endpoints.push(endpoint);
     }
 
-    pub fn add_cl_monitoring_endpoint(
-        &mut self,
-        location: &'a str,
-        reliability: &'a str,
-        endpoint: String,
-    ) {
-        self.cl_monitoring_endpoints
-            .entry((location, reliability))
-            .or_default()
-            .push(endpoint);
+    pub fn add_monitoring_endpoint(&mut self, endpoint: MonitoringEndpoint) {
+        self.monitoring_endpoints.push(endpoint);
+    }
+
+    /// The static peering settings, if clients should not rely on discovery.
+    pub fn static_peering(&self) -> Option<&'a StaticPeering> {
+        self.config.static_peering.as_ref()
+    }
+
+    pub fn add_el_identity(&mut self, node: &NodeInfo<'a>, address: String, id: String) {
+        self.el_identities.push(PeerIdentity {
+            host: node.name.clone(),
+            client_id: node.client_id.to_string(),
+            ip: node.ip,
+            location: node.location,
+            tag: node.tag,
+            address,
+            id,
+        });
+    }
+
+    pub fn add_cl_identity(&mut self, node: &NodeInfo<'a>, address: String, id: String) {
+        self.cl_identities.push(PeerIdentity {
+            host: node.name.clone(),
+            client_id: node.client_id.to_string(),
+            ip: node.ip,
+            location: node.location,
+            tag: node.tag,
+            address,
+            id,
+        });
+    }
+
+    pub fn el_identities(&self) -> &[PeerIdentity<'a>] {
+        self.el_identities.as_slice()
+    }
+
+    pub fn cl_identities(&self) -> &[PeerIdentity<'a>] {
+        self.cl_identities.as_slice()
+    }
+
+    /// The static peers of the EL client added to the given node.
+    pub fn el_static_peers_for(&self, node: &NodeInfo) -> Vec<&PeerIdentity<'a>> {
+        peers_for(
+            &self.el_identities,
+            &self.el_peer_graph,
+            &node.name,
+            node.client_id,
+        )
+    }
+
+    /// The static peers of the CL client added to the given node.
+    pub fn cl_static_peers_for(&self, node: &NodeInfo) -> Vec<&PeerIdentity<'a>> {
+        peers_for(
+            &self.cl_identities,
+            &self.cl_peer_graph,
+            &node.name,
+            node.client_id,
+        )
+    }
+
+    /// Connects the registered static peers, once all of them are known.
+    fn build_peer_graphs(&mut self) {
+        let Some(config) = self.static_peering() else {
+            return;
+        };
+        let locations = |peers: &[PeerIdentity<'a>]| -> Vec<&'a str> {
+            peers.iter().map(|peer| peer.location).collect()
+        };
+        self.el_peer_graph = build_graph(config, &locations(&self.el_identities), &mut self.rng);
+        self.cl_peer_graph = build_graph(config, &locations(&self.cl_identities), &mut self.rng);
+    }
+
+    pub fn add_prometheus_shard(&mut self, target: String) {
+        self.prometheus_shards.push(target);
     }
 
     pub fn num_el_clients(&self) -> usize {
@@ -265,3 +626,17 @@ impl<'a> SimulationContext<'a> {
         self.num_cl_clients
     }
 }
+
+fn peers_for<'p, 'a>(
+    peers: &'p [PeerIdentity<'a>],
+    graph: &[Vec<usize>],
+    host: &str,
+    client_id: &str,
+) -> Vec<&'p PeerIdentity<'a>> {
+    peers
+        .iter()
+        .position(|peer| peer.host == host && peer.client_id == client_id)
+        .and_then(|idx| graph.get(idx))
+        .map(|neighbours| neighbours.iter().map(|&idx| &peers[idx]).collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,167 @@
+//! Static peering: node identities generated by ethshadow, and peer graphs connecting them.
+
+use crate::config::ethshadow::{PeerTopology, StaticPeering};
+use libsecp256k1::{PublicKey, SecretKey};
+use rand::prelude::*;
+use std::collections::BTreeSet;
+
+/// A secp256k1 p2p identity, as used by both execution and consensus clients.
+pub struct NodeKey(SecretKey);
+
+impl NodeKey {
+    pub fn random<R: Rng>(rng: &mut R) -> Self {
+        NodeKey(SecretKey::random(rng))
+    }
+
+    /// Parses a raw secret key, returns `None` if it is out of range.
+    pub fn from_secret_bytes(bytes: &[u8; 32]) -> Option<Self> {
+        SecretKey::parse(bytes).ok().map(NodeKey)
+    }
+
+    pub fn secret_bytes(&self) -> [u8; 32] {
+        self.0.serialize()
+    }
+
+    pub fn secret_hex(&self) -> String {
+        hex::encode(self.secret_bytes())
+    }
+
+    /// The node id used in enodes: the uncompressed public key without prefix, hex encoded.
+    pub fn enode_id(&self) -> String {
+        hex::encode(&PublicKey::from_secret_key(&self.0).serialize()[1..])
+    }
+
+    /// The libp2p peer id: an identity multihash of the protobuf encoded public key, in base58.
+    pub fn peer_id(&self) -> String {
+        let public_key = PublicKey::from_secret_key(&self.0).serialize_compressed();
+        // protobuf: field 1 (key type) = 2 (secp256k1), field 2 (data) = compressed key
+        let mut encoded = vec![0x08, 0x02, 0x12, public_key.len() as u8];
+        encoded.extend_from_slice(&public_key);
+        // multihash: code 0 (identity), length
+        let mut multihash = vec![0x00, encoded.len() as u8];
+        multihash.extend(encoded);
+        bs58::encode(multihash).into_string()
+    }
+}
+
+/// Builds an undirected peer graph for clients in the given locations. Returns the sorted
+/// neighbours of each client.
+pub fn build_graph<R: Rng>(
+    config: &StaticPeering,
+    locations: &[&str],
+    rng: &mut R,
+) -> Vec<Vec<usize>> {
+    let mut graph = vec![BTreeSet::new(); locations.len()];
+    let all: Vec<usize> = (0..locations.len()).collect();
+    match config.topology {
+        PeerTopology::RandomRegular => connect_randomly(&mut graph, &all, config.degree, rng),
+        PeerTopology::SmallWorld => {
+            small_world(&mut graph, config.degree, config.rewire_probability, rng)
+        }
+        PeerTopology::Location => {
+            let mut groups: Vec<(&str, Vec<usize>)> = vec![];
+            for (idx, location) in locations.iter().enumerate() {
+                match groups.iter_mut().find(|(name, _)| name == location) {
+                    Some((_, members)) => members.push(idx),
+                    None => groups.push((location, vec![idx])),
+                }
+            }
+            for (_, members) in &groups {
+                connect_randomly(&mut graph, members, config.degree.saturating_sub(1), rng);
+            }
+            if groups.len() > 1 {
+                for idx in 0..locations.len() {
+                    let others: Vec<usize> = (0..locations.len())
+                        .filter(|&other| locations[other] != locations[idx])
+                        .collect();
+                    if let Some(&other) = others.choose(rng) {
+                        connect(&mut graph, idx, other);
+                    }
+                }
+            }
+        }
+    }
+    graph
+        .into_iter()
+        .map(|peers| peers.into_iter().collect())
+        .collect()
+}
+
+fn connect(graph: &mut [BTreeSet<usize>], a: usize, b: usize) {
+    graph[a].insert(b);
+    graph[b].insert(a);
+}
+
+/// Connects each member to random other members until it has `degree` peers among them, as
+/// long as there are members with free slots left.
+fn connect_randomly<R: Rng>(
+    graph: &mut [BTreeSet<usize>],
+    members: &[usize],
+    degree: usize,
+    rng: &mut R,
+) {
+    let mut is_member = vec![false; graph.len()];
+    for &member in members {
+        is_member[member] = true;
+    }
+    let mut count = vec![0; graph.len()];
+    for &member in members {
+        count[member] = graph[member]
+            .iter()
+            .filter(|&&peer| is_member[peer])
+            .count();
+    }
+
+    let mut order = members.to_vec();
+    order.shuffle(rng);
+    for &node in &order {
+        while count[node] < degree {
+            let candidates: Vec<usize> = members
+                .iter()
+                .copied()
+                .filter(|&other| {
+                    other != node && count[other] < degree && !graph[node].contains(&other)
+                })
+                .collect();
+            let Some(&other) = candidates.choose(rng) else {
+                break;
+            };
+            connect(graph, node, other);
+            count[node] += 1;
+            count[other] += 1;
+        }
+    }
+}
+
+/// Watts-Strogatz: a ring lattice with `degree / 2` neighbours on each side, where each edge is
+/// rewired to a random node with the given probability.
+fn small_world<R: Rng>(
+    graph: &mut [BTreeSet<usize>],
+    degree: usize,
+    rewire_probability: f64,
+    rng: &mut R,
+) {
+    let n = graph.len();
+    if n < 2 {
+        return;
+    }
+    let half = (degree / 2).clamp(1, (n - 1) / 2 + 1);
+    for node in 0..n {
+        for offset in 1..=half {
+            let neighbour = (node + offset) % n;
+            if neighbour == node {
+                continue;
+            }
+            if rng.gen_bool(rewire_probability) {
+                let candidates: Vec<usize> = (0..n)
+                    .filter(|&other| other != node && !graph[node].contains(&other))
+                    .collect();
+                if let Some(&other) = candidates.choose(rng) {
+                    connect(graph, node, other);
+                    continue;
+                }
+            }
+            connect(graph, node, neighbour);
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! A receiver for Prometheus' remote write protocol, which appends all received samples to a file.
+//! It runs inside the simulation, so it only needs to understand what Prometheus sends:
+//! snappy-compressed `WriteRequest` protobuf messages, POSTed via HTTP.
+
+use crate::Error;
+use log::warn;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+use tiny_http::{Request, Response, Server};
+
+/// Upper bound for the size of a request body, compressed or not. Prometheus sends at most a few
+/// thousand samples per request, which is far below this.
+pub const MAX_REQUEST_SIZE: usize = 32 * 1024 * 1024;
+
+/// A single sample, as written to the output file, one JSON object per line.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub labels: BTreeMap<String, String>,
+    /// Milliseconds since the Unix epoch
+    pub timestamp: i64,
+    pub value: f64,
+}
+
+/// The parts of `prometheus.WriteRequest` we are interested in. Metadata and exemplars are
+/// ignored.
+#[derive(Clone, PartialEq, Message)]
+pub struct WriteRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub timeseries: Vec<TimeSeries>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TimeSeries {
+    #[prost(message, repeated, tag = "1")]
+    pub labels: Vec<Label>,
+    #[prost(message, repeated, tag = "2")]
+    pub samples: Vec<ProtoSample>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Label {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, tag = "2")]
+    pub value: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ProtoSample {
+    #[prost(double, tag = "1")]
+    pub value: f64,
+    #[prost(int64, tag = "2")]
+    pub timestamp: i64,
+}
+
+/// Accepts remote write requests on the given port forever, appending the samples to `output`.
+pub fn receive(port: u16, output: &Path) -> Result<(), Error> {
+    let server = Server::http(("0.0.0.0", port)).map_err(std::io::Error::other)?;
+    let mut output = BufWriter::new(File::create(output)?);
+    for request in server.incoming_requests() {
+        handle(request, &mut output)?;
+    }
+    Ok(())
+}
+
+fn handle(mut request: Request, output: &mut BufWriter<File>) -> Result<(), Error> {
+    let samples = read_body(&mut request).and_then(|body| decode(&body));
+    let status = match samples {
+        Ok(samples) => {
+            for sample in samples {
+                serde_json::to_writer(&mut *output, &sample)?;
+                writeln!(output)?;
+            }
+            output.flush()?;
+            204
+        }
+        Err(e) => {
+            // Prometheus does not retry client errors, so the batch is dropped
+            warn!("Dropping invalid remote write request: {e}");
+            400
+        }
+    };
+    if let Err(e) = request.respond(Response::empty(status)) {
+        warn!("Unable to respond to remote write request: {e}");
+    }
+    Ok(())
+}
+
+/// Reads the body, which may be sent with a content length or chunked, up to
+/// [`MAX_REQUEST_SIZE`].
+fn read_body(request: &mut Request) -> Result<Vec<u8>, Error> {
+    if request.body_length().unwrap_or(0) > MAX_REQUEST_SIZE {
+        return Err(too_large());
+    }
+    let mut body = vec![];
+    request
+        .as_reader()
+        .take(MAX_REQUEST_SIZE as u64 + 1)
+        .read_to_end(&mut body)?;
+    if body.len() > MAX_REQUEST_SIZE {
+        return Err(too_large());
+    }
+    Ok(body)
+}
+
+/// Decodes the samples of a snappy-compressed `WriteRequest`. Prometheus uses the snappy block
+/// format, not the framing format.
+pub fn decode(body: &[u8]) -> Result<Vec<Sample>, Error> {
+    let invalid_snappy =
+        |e: snap::Error| Error::InvalidRemoteWrite(format!("invalid snappy data: {e}"));
+    if snap::raw::decompress_len(body).map_err(invalid_snappy)? > MAX_REQUEST_SIZE {
+        return Err(too_large());
+    }
+    let request = snap::raw::Decoder::new()
+        .decompress_vec(body)
+        .map_err(invalid_snappy)?;
+    let request = WriteRequest::decode(request.as_slice())
+        .map_err(|e| Error::InvalidRemoteWrite(format!("invalid protobuf message: {e}")))?;
+    Ok(request
+        .timeseries
+        .into_iter()
+        .flat_map(|series| {
+            let labels: BTreeMap<_, _> = series
+                .labels
+                .into_iter()
+                .map(|label| (label.name, label.value))
+                .collect();
+            series.samples.into_iter().map(move |sample| Sample {
+                labels: labels.clone(),
+                timestamp: sample.timestamp,
+                value: sample.value,
+            })
+        })
+        .collect())
+}
+
+fn too_large() -> Error {
+    Error::InvalidRemoteWrite(format!("request is larger than {MAX_REQUEST_SIZE} bytes"))
+}
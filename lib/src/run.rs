@@ -0,0 +1,185 @@
+//! Supervised runs: instead of replacing our process with Shadow, we spawn it, follow its log to
+//! report progress, and write a summary of the run when Shadow exits.
+//!
+//! Whether the run succeeded is decided by Shadow's exit status: Shadow itself checks that each
+//! process reached its `expected_final_state` and fails otherwise. To tell which processes
+//! failed, we collect the exit codes Shadow writes next to the output of each process.
+
+use crate::{Error, ShadowInvocation};
+use humantime_serde::re::humantime::format_duration;
+use log::{info, warn};
+use serde::Serialize;
+use std::fs::{read_dir, read_to_string, File};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::os::unix::process::ExitStatusExt;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+pub const SUMMARY_FILE: &str = "run_summary.json";
+
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Debug)]
+pub struct RunSummary {
+    /// Whether Shadow exited successfully, i.e. all processes reached their expected final state.
+    pub success: bool,
+    pub exit_code: Option<i32>,
+    /// The signal that terminated Shadow, if any.
+    pub signal: Option<i32>,
+    pub wall_clock_seconds: f64,
+    /// The latest simulated time seen in Shadow's log.
+    pub simulated_seconds: f64,
+    pub stop_time_seconds: Option<f64>,
+    /// The processes that exited during the simulation, as recorded by Shadow.
+    pub exited_processes: Vec<ProcessExit>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct ProcessExit {
+    pub host: String,
+    /// Name of the process' output files, e.g. `geth.1000`.
+    pub process: String,
+    /// `None` if Shadow's exit code file could not be parsed.
+    pub exit_code: Option<i32>,
+}
+
+/// Runs Shadow, forwarding its output to `output`, and writes a `run_summary.json` into
+/// `output_path`.
+pub fn supervise<W: Write>(
+    invocation: ShadowInvocation,
+    output_path: &Path,
+    mut output: W,
+) -> Result<RunSummary, Error> {
+    let stop_time = invocation.stop_time();
+    let start = Instant::now();
+    let mut child = invocation.command().stdout(Stdio::piped()).spawn()?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+
+    let mut simulated = Duration::ZERO;
+    let mut last_progress = start;
+    let mut reader = BufReader::new(stdout);
+    let mut buffer = vec![];
+    loop {
+        buffer.clear();
+        match reader.read_until(b'\n', &mut buffer) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => {
+                // we can not follow Shadow anymore, but still want to record how it ended
+                warn!("Unable to read the output of Shadow: {e}");
+                let _ = child.kill();
+                break;
+            }
+        }
+        if let Err(e) = output.write_all(&buffer) {
+            warn!("Unable to forward the output of Shadow: {e}");
+        }
+        let line = String::from_utf8_lossy(&buffer);
+        if let Some(time) = parse_simulated_time(&line) {
+            simulated = simulated.max(time);
+        }
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            report_progress(simulated, stop_time, start.elapsed());
+        }
+    }
+    let status = child.wait()?;
+    let wall_clock = start.elapsed();
+    let _ = output.flush();
+
+    let summary = RunSummary {
+        success: status.success(),
+        exit_code: status.code(),
+        signal: status.signal(),
+        wall_clock_seconds: wall_clock.as_secs_f64(),
+        simulated_seconds: simulated.as_secs_f64(),
+        stop_time_seconds: stop_time.map(|time| time.as_secs_f64()),
+        exited_processes: exited_processes(&output_path.join("shadow").join("hosts"))?,
+    };
+    serde_json::to_writer_pretty(File::create(output_path.join(SUMMARY_FILE))?, &summary)?;
+
+    if summary.success {
+        info!(
+            "Shadow finished after {}",
+            format_duration(Duration::from_secs(wall_clock.as_secs()))
+        );
+    } else {
+        warn!("Shadow failed with {status}");
+        for process in &summary.exited_processes {
+            match process.exit_code {
+                Some(0) => {}
+                Some(code) => warn!("{} on {} exited with {code}", process.process, process.host),
+                None => warn!(
+                    "{} on {} has an unknown exit code",
+                    process.process, process.host
+                ),
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// Extracts the simulated time from a line of Shadow's log, which has the format
+/// `<real time> [<thread>] <simulated time> [<level>] ...`, e.g.
+/// `00:00:01.234567 [12:shadow-worker] 00:05:00.000000000 [INFO] ...`.
+pub fn parse_simulated_time(line: &str) -> Option<Duration> {
+    let time = line.split_whitespace().nth(2)?;
+    let (hms, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let mut parts = hms.splitn(3, ':').map(str::parse::<u64>);
+    let (Some(Ok(h)), Some(Ok(m)), Some(Ok(s))) = (parts.next(), parts.next(), parts.next()) else {
+        return None;
+    };
+    let nanos = if fraction.is_empty() {
+        0
+    } else {
+        // pad or cut to nanosecond precision
+        format!("{fraction:0<9}").get(..9)?.parse().ok()?
+    };
+    Some(Duration::new(h * 3600 + m * 60 + s, nanos))
+}
+
+/// Reads the `<process>.exitcode` files Shadow writes for each exited process into the directory
+/// of its host.
+pub fn exited_processes(hosts_dir: &Path) -> Result<Vec<ProcessExit>, Error> {
+    let hosts = match read_dir(hosts_dir) {
+        Ok(hosts) => hosts,
+        // Shadow failed before starting any process
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    let mut processes = vec![];
+    for host in hosts {
+        let host = host?;
+        if !host.file_type()?.is_dir() {
+            continue;
+        }
+        for file in read_dir(host.path())? {
+            let path = file?.path();
+            if path.extension().is_some_and(|ext| ext == "exitcode") {
+                let Some(process) = path.file_stem() else {
+                    continue;
+                };
+                processes.push(ProcessExit {
+                    host: host.file_name().to_string_lossy().into_owned(),
+                    process: process.to_string_lossy().into_owned(),
+                    exit_code: read_to_string(&path)?.trim().parse().ok(),
+                });
+            }
+        }
+    }
+    processes.sort_by(|a, b| (&a.host, &a.process).cmp(&(&b.host, &b.process)));
+    Ok(processes)
+}
+
+fn report_progress(simulated: Duration, stop_time: Option<Duration>, wall_clock: Duration) {
+    let speed = simulated.as_secs_f64() / wall_clock.as_secs_f64();
+    let simulated = format_duration(Duration::from_secs(simulated.as_secs()));
+    match stop_time {
+        Some(stop_time) => info!(
+            "Simulated {simulated} of {} ({speed:.2}x real time)",
+            format_duration(stop_time)
+        ),
+        None => info!("Simulated {simulated} ({speed:.2}x real time)"),
+    }
+}
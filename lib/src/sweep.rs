@@ -0,0 +1,246 @@
+//! Parameter sweeps: generating one simulation per combination of values given in the `sweep`
+//! section of the config.
+//!
+//! ```yaml
+//! sweep:
+//!   ethereum.validators: [100, 1000]
+//!   general.seed: [{range: [1, 5]}]
+//!   ethereum.nodes.1.reliabilities: [[reliable], [reliable, home]]
+//! ```
+//!
+//! Each key is a path into the config, with numbers indexing into lists. A value of the form
+//! `{range: [a, b]}` is expanded into the integers from `a` to `b`, both included.
+
+use crate::{generate, Error};
+use itertools::Itertools;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::ffi::OsString;
+use std::fs::{create_dir, read, remove_dir_all, File};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+pub const SWEEP_KEY: &str = "sweep";
+pub const MANIFEST_FILE: &str = "sweep.json";
+const RANGE_KEY: &str = "range";
+/// Upper bound for the number of values a single range may expand to
+pub const MAX_RANGE_LEN: u64 = 1000;
+
+pub struct Variant {
+    pub name: String,
+    pub parameters: Vec<(String, Value)>,
+    pub config: Value,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GeneratedVariant {
+    pub name: String,
+    pub directory: PathBuf,
+    #[serde(
+        serialize_with = "serialize_parameters",
+        deserialize_with = "deserialize_parameters"
+    )]
+    pub parameters: Vec<(String, Value)>,
+    pub shadow_command: Vec<OsString>,
+}
+
+fn serialize_parameters<S: serde::Serializer>(
+    parameters: &[(String, Value)],
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    s.collect_map(parameters.iter().map(|(path, value)| (path, value)))
+}
+
+fn deserialize_parameters<'de, D: serde::Deserializer<'de>>(
+    d: D,
+) -> Result<Vec<(String, Value)>, D::Error> {
+    // a `Mapping` keeps the order of the parameters
+    Mapping::deserialize(d)?
+        .into_iter()
+        .map(|(path, value)| match path {
+            Value::String(path) => Ok((path, value)),
+            _ => Err(serde::de::Error::custom("parameter paths must be strings")),
+        })
+        .collect()
+}
+
+/// Removes the sweep section from the config and returns the config for each combination of the
+/// swept values. If there is no sweep section, a single variant is returned.
+pub fn expand(mut config: Value) -> Result<Vec<Variant>, Error> {
+    let axes = match config
+        .as_mapping_mut()
+        .ok_or_else(|| Error::ExpectedOtherType("<root>".to_string()))?
+        .remove(SWEEP_KEY)
+    {
+        None | Some(Value::Null) => Mapping::new(),
+        Some(Value::Mapping(axes)) => axes,
+        Some(_) => return Err(Error::ExpectedOtherType(SWEEP_KEY.to_string())),
+    };
+
+    let axes: Vec<(String, Vec<Value>)> = axes
+        .into_iter()
+        .map(|(path, values)| {
+            let Value::String(path) = path else {
+                return Err(Error::ExpectedOtherType(format!("{SWEEP_KEY} key")));
+            };
+            let Value::Sequence(values) = values else {
+                return Err(Error::ExpectedOtherType(format!("{SWEEP_KEY}.{path}")));
+            };
+            let values = values
+                .into_iter()
+                .map(expand_range)
+                .flatten_ok()
+                .try_collect()?;
+            Ok((path, values))
+        })
+        .try_collect()?;
+
+    let combinations: Vec<Vec<(String, Value)>> = axes
+        .iter()
+        .map(|(path, values)| {
+            values
+                .iter()
+                .map(move |value| (path.clone(), value.clone()))
+        })
+        .multi_cartesian_product()
+        .collect();
+    // without axes, the product is empty, but we still want to generate the base config
+    let combinations = if axes.is_empty() {
+        vec![vec![]]
+    } else {
+        combinations
+    };
+
+    let width = combinations.len().saturating_sub(1).to_string().len();
+    combinations
+        .into_iter()
+        .enumerate()
+        .map(|(idx, parameters)| {
+            let mut config = config.clone();
+            for (path, value) in &parameters {
+                set_path(&mut config, path, value.clone())?;
+            }
+            Ok(Variant {
+                name: format!("variant{idx:0width$}"),
+                parameters,
+                config,
+            })
+        })
+        .collect()
+}
+
+/// Generates a data directory for each variant of the given config within `output_path`, and
+/// writes a manifest describing the variants.
+///
+/// The manifest is updated after each variant, so that an interrupted or failed sweep can be
+/// resumed by running it again on the same directory: variants listed in the manifest are
+/// skipped, and leftovers of other variants are removed and generated again.
+pub fn generate_sweep(
+    config: Value,
+    output_path: &Path,
+    use_existing_dir: bool,
+) -> Result<Vec<GeneratedVariant>, Error> {
+    let variants = expand(config)?;
+    let manifest_path = output_path.join(MANIFEST_FILE);
+    let mut generated = match create_dir(output_path) {
+        Ok(()) => vec![],
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => match read(&manifest_path) {
+            Ok(manifest) => serde_json::from_slice(&manifest)?,
+            Err(e) if e.kind() == ErrorKind::NotFound && use_existing_dir => vec![],
+            Err(e) if e.kind() == ErrorKind::NotFound => return Err(Error::OutputFolderExists),
+            Err(e) => return Err(e.into()),
+        },
+        Err(e) => return Err(e.into()),
+    };
+    write_manifest(&manifest_path, &generated)?;
+
+    let total = variants.len();
+    for (idx, variant) in variants.into_iter().enumerate() {
+        if let Some(existing) = generated.iter().find(|v| v.name == variant.name) {
+            if existing.parameters != variant.parameters {
+                return Err(Error::SweepChanged(variant.name));
+            }
+            info!("Skipping {}, it was already generated", variant.name);
+            continue;
+        }
+
+        info!("Generating {} ({} of {total})", variant.name, idx + 1);
+        let directory = output_path.join(&variant.name);
+        match remove_dir_all(&directory) {
+            Ok(()) => info!("Removed incomplete {}", directory.display()),
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        let invocation = match generate(variant.config, &directory, use_existing_dir) {
+            Ok(invocation) => invocation,
+            Err(e) => {
+                // do not leave a half generated variant behind, it is generated again on rerun
+                if let Err(e) = remove_dir_all(&directory) {
+                    if e.kind() != ErrorKind::NotFound {
+                        warn!("Unable to remove {}: {e}", directory.display());
+                    }
+                }
+                return Err(e);
+            }
+        };
+        generated.push(GeneratedVariant {
+            name: variant.name,
+            directory: directory.canonicalize()?,
+            parameters: variant.parameters,
+            shadow_command: invocation.command_line(),
+        });
+        write_manifest(&manifest_path, &generated)?;
+    }
+
+    Ok(generated)
+}
+
+fn write_manifest(path: &Path, generated: &[GeneratedVariant]) -> Result<(), Error> {
+    serde_json::to_writer_pretty(File::create(path)?, generated)?;
+    Ok(())
+}
+
+/// Expands `{range: [a, b]}` into the integers from `a` to `b`. All other values are kept.
+fn expand_range(value: Value) -> Result<Vec<Value>, Error> {
+    let Value::Mapping(mapping) = &value else {
+        return Ok(vec![value]);
+    };
+    let Some(range) = mapping.get(RANGE_KEY) else {
+        return Ok(vec![value]);
+    };
+    let invalid = || Error::InvalidSweepRange(serde_json::to_string(&value).unwrap_or_default());
+    if mapping.len() != 1 {
+        return Err(invalid());
+    }
+    let bounds = match range.as_sequence().map(Vec::as_slice) {
+        Some([start, end]) => start.as_i64().zip(end.as_i64()),
+        _ => None,
+    };
+    let Some((start, end)) = bounds.filter(|(start, end)| start <= end) else {
+        return Err(invalid());
+    };
+    if end.abs_diff(start) >= MAX_RANGE_LEN {
+        return Err(invalid());
+    }
+    Ok((start..=end).map(Value::from).collect())
+}
+
+fn set_path(config: &mut Value, path: &str, value: Value) -> Result<(), Error> {
+    let mut current = config;
+    for segment in path.split('.') {
+        current = match current {
+            Value::Mapping(mapping) => mapping
+                .entry(segment.into())
+                .or_insert_with(|| Value::Mapping(Mapping::new())),
+            Value::Sequence(seq) => segment
+                .parse::<usize>()
+                .ok()
+                .and_then(|idx| seq.get_mut(idx))
+                .ok_or_else(|| Error::InvalidSweepPath(path.to_string()))?,
+            _ => return Err(Error::InvalidSweepPath(path.to_string())),
+        };
+    }
+    *current = value;
+    Ok(())
+}
@@ -33,3 +33,11 @@ fn log_lines<R: Read>(stream: &str, read: Option<R>) {
         debug!("{stream}: {line}");
     }
 }
+
+/// A simple hash (FNV-1a) that is stable across Rust versions, so that anything derived from it is
+/// reproducible.
+pub fn stable_hash(value: &str) -> u64 {
+    value.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
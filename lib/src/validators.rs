@@ -4,10 +4,11 @@ use crate::config::EthShadowConfig;
 use crate::utils::log_and_wait;
 use crate::Error;
 use itertools::Itertools;
-use log::info;
+use log::{info, warn};
 use std::cmp::min;
-use std::ffi::OsString;
-use std::fs::read_dir;
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::fs::{read_dir, File};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use users::get_current_uid;
@@ -32,7 +33,7 @@ impl ValidatorManager {
         let mut anys = 0;
         for node in nodes {
             let count = node.count;
-            for client in &node.clients {
+            for (_, client) in &node.clients {
                 match client.validator_demand() {
                     ValidatorDemand::Count(val_count) => requested += val_count * count,
                     ValidatorDemand::Any => anys += count,
@@ -46,8 +47,8 @@ impl ValidatorManager {
                 return Err(Error::MoreValidatorsRequested(validators, requested));
             };
             validator_count = validators;
-            if anys != 0 {
-                val_for_each_any = remaining / anys;
+            if let Some(per_any) = remaining.checked_div(anys) {
+                val_for_each_any = per_any;
                 remainder = remaining % anys;
             } else if remaining != 0 {
                 return Err(Error::LeftoverValidators);
@@ -149,20 +150,54 @@ fn generate(
             return Err(Error::ChildProcessFailure(image_name.to_string()));
         }
         let base_path = output_path.join(format!("validator_keys_{idx}"));
-        for validator in read_dir(base_path.join("keys"))?.map_ok(|e| Validator {
-            base_path: base_path.clone(),
-            key: e.file_name(),
-        }) {
-            validators.push(validator?);
-        }
+        let indices = read_indices(&base_path, validators.len());
+        let mut batch: Vec<Validator> = read_dir(base_path.join("keys"))?
+            .map_ok(|e| Validator {
+                base_path: base_path.clone(),
+                index: indices.get(&normalize_pubkey(&e.file_name())).copied(),
+                key: e.file_name(),
+            })
+            .try_collect()?;
+        // read_dir has no defined order, so sort by index if we know it to stay deterministic
+        batch.sort_by_key(|validator| validator.index);
+        validators.append(&mut batch);
         idx += 1;
     }
     Ok(validators)
 }
 
+/// Reads the order in which eth2-val-tools derived the keys of a batch, which is the order of the
+/// validators in the genesis state. Returns an empty map if the file is unavailable.
+fn read_indices(base_path: &Path, first_index: usize) -> HashMap<String, usize> {
+    let Ok(file) = File::open(base_path.join("pubkeys.json")) else {
+        return HashMap::new();
+    };
+    let pubkeys: Vec<String> = match serde_json::from_reader(file) {
+        Ok(pubkeys) => pubkeys,
+        Err(e) => {
+            warn!("Unable to read validator pubkeys.json: {e}");
+            return HashMap::new();
+        }
+    };
+    pubkeys
+        .iter()
+        .enumerate()
+        .map(|(idx, pubkey)| (normalize_pubkey(pubkey), first_index + idx))
+        .collect()
+}
+
+fn normalize_pubkey<S: AsRef<OsStr>>(pubkey: &S) -> String {
+    let pubkey = pubkey.as_ref().to_string_lossy().to_lowercase();
+    match pubkey.strip_prefix("0x") {
+        Some(pubkey) => pubkey.to_string(),
+        None => pubkey,
+    }
+}
+
 pub struct Validator {
     base_path: PathBuf,
     key: OsString,
+    index: Option<usize>,
 }
 
 impl Validator {
@@ -173,4 +208,14 @@ impl Validator {
     pub fn key(&self) -> &OsString {
         &self.key
     }
+
+    /// The public key, with `0x` prefix.
+    pub fn pubkey(&self) -> String {
+        format!("0x{}", normalize_pubkey(&self.key))
+    }
+
+    /// The index of the validator in the genesis state, if known.
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
 }
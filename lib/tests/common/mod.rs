@@ -0,0 +1,102 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use enr::k256::ecdsa::SigningKey;
+use enr::Enr;
+use ethshadow::config::ethshadow::DEFAULT_GENESIS_FORK_VERSION;
+use ethshadow::config::{FullConfig, ShadowConfig};
+use ethshadow::manifest::{ClientRecord, HostRecord, Manifest};
+use ethshadow::network_graph::generate_network_graph;
+use ethshadow::node::NodeManager;
+use ethshadow::validators::ValidatorManager;
+use ethshadow::Error;
+use std::fs::{read, read_to_string};
+use std::net::Ipv4Addr;
+use std::path::Path;
+
+/// Generates the hosts of the given config into `dir`, like `ethshadow::generate`, but without
+/// generating the genesis, which requires Docker. The config must not request any validators.
+pub fn generate_nodes(config: &str, dir: &Path) -> Result<(ShadowConfig, Manifest), Error> {
+    let FullConfig {
+        mut ethshadow_config,
+        mut shadow_config,
+    } = config.try_into()?;
+    ethshadow_config.add_default_builtins();
+    shadow_config.apply_defaults()?;
+    let graph = generate_network_graph(&ethshadow_config)?;
+    let nodes = ethshadow_config.desugar_nodes()?;
+    let validators = ValidatorManager::new(&ethshadow_config, &nodes, dir)?;
+    let mut node_manager = NodeManager::new(
+        dir.to_path_buf(),
+        &nodes,
+        &mut shadow_config,
+        graph.network_graph,
+        validators,
+        &ethshadow_config,
+    );
+    node_manager.generate_nodes()?;
+    let manifest = node_manager.manifest().clone();
+    Ok((shadow_config, manifest))
+}
+
+/// The arguments of the process of the given host whose path ends with `executable`.
+pub fn process_args(config: &ShadowConfig, host: &str, executable: &str) -> String {
+    config.0["hosts"][host]["processes"]
+        .as_sequence()
+        .expect("host has processes")
+        .iter()
+        .find(|process| {
+            process["path"]
+                .as_str()
+                .is_some_and(|path| path.ends_with(executable))
+        })
+        .unwrap_or_else(|| panic!("{host} runs no {executable}"))["args"]
+        .as_str()
+        .expect("args are a string")
+        .to_string()
+}
+
+/// A manifest entry for a host in `dir` running a single client of the given type.
+pub fn host(dir: &Path, name: &str, ip: Ipv4Addr, client_type: &str) -> HostRecord {
+    HostRecord {
+        name: name.to_string(),
+        ip,
+        ipv6: None,
+        network_node_id: 0,
+        location: "europe".to_string(),
+        reliability: "home".to_string(),
+        tag: None,
+        directory: dir.join(name),
+        shadow_directory: dir.join("shadow/hosts").join(name),
+        clients: vec![ClientRecord {
+            id: client_type.to_string(),
+            client_type: client_type.to_string(),
+            ports: Default::default(),
+            validators: vec![],
+            enode: None,
+            enr: None,
+            peer_id: None,
+        }],
+    }
+}
+
+/// Parses the ENR in the network directory of a boot node, checking that it is signed with the
+/// key the boot node reads from the same directory and carries the `eth2` entry of the default
+/// genesis fork version.
+pub fn network_enr(network_dir: &Path) -> Enr<SigningKey> {
+    let enr: Enr<SigningKey> = read_to_string(network_dir.join("enr.dat"))
+        .unwrap()
+        .parse()
+        .expect("valid and correctly signed ENR");
+    let key = SigningKey::from_slice(&read(network_dir.join("key")).unwrap()).unwrap();
+    assert_eq!(&enr.public_key(), key.verifying_key());
+    let eth2 = enr.get("eth2").expect("eth2 entry");
+    // fork digest, genesis fork version, no next fork
+    assert_eq!(eth2.len(), 16);
+    assert_eq!(
+        eth2[4..8],
+        hex::decode(&DEFAULT_GENESIS_FORK_VERSION[2..]).unwrap()
+    );
+    assert_eq!(eth2[8..], [0xff; 8]);
+    enr
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use common::{generate_nodes, network_enr, process_args};
+use ethshadow::addressing::IpAllocator;
+use ethshadow::config::ethshadow::{IpAllocation, Subnet};
+use ethshadow::Error;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::HashSet;
+use std::net::Ipv4Addr;
+
+#[test]
+fn subnet_parsing() {
+    let subnet: Subnet = "44.12.7.9/16".parse().unwrap();
+    assert_eq!(subnet.network(), Ipv4Addr::new(44, 12, 0, 0));
+    assert_eq!(subnet.prefix_len(), 16);
+    assert_eq!(subnet.to_string(), "44.12.0.0/16");
+    assert_eq!(subnet.host_count(), 65534);
+    assert!("44.12.0.0".parse::<Subnet>().is_err());
+    assert!("44.12.0.0/31".parse::<Subnet>().is_err());
+    assert!("0.0.0.0/0".parse::<Subnet>().is_err());
+    assert!("44.0.0.0/7".parse::<Subnet>().is_err());
+    assert!("44.12.0/16".parse::<Subnet>().is_err());
+}
+
+#[test]
+fn subnet_per_location() -> Result<(), Error> {
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut allocator = IpAllocator::new(IpAllocation::SubnetPerLocation { prefix_len: 16 });
+    let europe: Vec<Ipv4Addr> = (0..10)
+        .map(|_| allocator.allocate("europe", None, &mut rng))
+        .collect::<Result<_, _>>()?;
+    let asia = allocator.allocate("east_asia", None, &mut rng)?;
+
+    let subnet = Subnet::new(europe[0], 16).unwrap();
+    assert!(europe
+        .iter()
+        .all(|&ip| Subnet::new(ip, 16).unwrap() == subnet));
+    assert_ne!(Subnet::new(asia, 16).unwrap(), subnet);
+    assert_eq!(allocator.allocated(), 11);
+    Ok(())
+}
+
+#[test]
+fn shared_subnets() -> Result<(), Error> {
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut allocator = IpAllocator::new(IpAllocation::SharedSubnets {
+        nodes_per_subnet: 3,
+        prefix_len: 24,
+    });
+    let subnets: Vec<Subnet> = (0..9)
+        .map(|_| {
+            let ip = allocator.allocate("europe", None, &mut rng)?;
+            assert!(!matches!(ip.octets()[3], 0 | 255));
+            Ok(Subnet::new(ip, 24).unwrap())
+        })
+        .collect::<Result<_, Error>>()?;
+    for chunk in subnets.chunks(3) {
+        assert!(chunk.iter().all(|subnet| *subnet == chunk[0]));
+    }
+    assert_eq!(subnets.iter().collect::<HashSet<_>>().len(), 3);
+    Ok(())
+}
+
+#[test]
+fn node_group_subnet() -> Result<(), Error> {
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut allocator = IpAllocator::new(IpAllocation::Random);
+    let pool: Subnet = "60.1.2.0/29".parse().unwrap();
+    let ips: HashSet<Ipv4Addr> = (0..6)
+        .map(|_| allocator.allocate("europe", Some(pool), &mut rng))
+        .collect::<Result<_, _>>()?;
+    assert_eq!(ips, (0..6).map(|idx| pool.host(idx)).collect());
+    assert!(matches!(
+        allocator.allocate("europe", Some(pool), &mut rng),
+        Err(Error::SubnetExhausted(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn dual_stack_client_args() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let (shadow, manifest) = generate_nodes(
+        "
+        ethereum:
+          addressing:
+            ip_version: dual_stack
+          clients:
+            fake_geth:
+              type: geth
+              executable: \"true\"
+          nodes:
+            - location: europe
+              reliability: reliable
+              clients:
+                cl: lighthouse_bootnode
+            - location: europe
+              reliability: reliable
+              clients:
+                el: fake_geth
+                cl: lighthouse
+            - location: europe
+              reliability: reliable
+              clients:
+                el: reth
+        ",
+        dir.path(),
+    )?;
+    let ipv6 = |host: usize| manifest.hosts[host].ipv6.expect("dual stack host");
+
+    // the boot node listens on both and advertises both in its ENR
+    let boot = process_args(&shadow, "node0", "lighthouse");
+    assert!(boot.contains("--listen-address 0.0.0.0 --listen-address :: --port6 4011"));
+    let enr = network_enr(&dir.path().join("node0/lighthouse_bootnode"));
+    assert_eq!(enr.ip4(), Some(manifest.hosts[0].ip));
+    assert_eq!(enr.ip6(), Some(ipv6(0)));
+    assert_eq!(enr.tcp6(), Some(4011));
+    assert_eq!(enr.udp6(), Some(4011));
+
+    let lighthouse = process_args(&shadow, "node1", "lighthouse");
+    assert!(lighthouse.contains(&format!("--enr-address {}", manifest.hosts[1].ip)));
+    assert!(lighthouse.contains(&format!(
+        "--listen-address 0.0.0.0 --listen-address :: --port6 31000 --enr-address {} \
+        --enr-udp6-port 31000 --enr-tcp6-port 31000",
+        ipv6(1)
+    )));
+
+    // the execution clients listen on both, but can only advertise IPv4
+    let geth = process_args(&shadow, "node1", "true");
+    assert!(geth.contains(&format!("--nat extip:{}", manifest.hosts[1].ip)));
+    let reth = process_args(&shadow, "node2", "reth");
+    assert!(reth.contains(&format!("--nat extip:{}", manifest.hosts[2].ip)));
+    assert!(reth.contains(&format!(
+        "--addr :: --discovery.addr :: --discovery.v5.addr.ipv6 {}",
+        ipv6(2)
+    )));
+    Ok(())
+}
+
+#[test]
+fn ipv4_client_args() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let (shadow, _) = generate_nodes(
+        "
+        ethereum:
+          nodes:
+            - location: europe
+              reliability: reliable
+              clients:
+                cl: lighthouse
+                el: reth
+        ",
+        dir.path(),
+    )?;
+    assert!(!process_args(&shadow, "node0", "lighthouse").contains("::"));
+    assert!(!process_args(&shadow, "node0", "reth").contains("::"));
+    Ok(())
+}
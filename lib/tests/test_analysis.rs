@@ -0,0 +1,264 @@
+mod common;
+
+use common::host;
+use ethshadow::analysis::analyze;
+use ethshadow::analysis::logs::{parse_geth_line, parse_lighthouse_line, ClEvent, ElHead};
+use ethshadow::analysis::propagation::percentiles;
+use ethshadow::analysis::topology::{read_placements, Placement};
+use ethshadow::manifest::{Manifest, MANIFEST_FILE};
+use std::fs::{create_dir_all, read_to_string, write};
+use std::net::Ipv4Addr;
+
+const NODE0_BN: &str = "\
+Jan 01 00:00:05.123 INFO Lighthouse started                      version: Lighthouse/v5.3.0
+Jan 01 00:00:12.000 INFO Searching for peers                     current_slot: 1, head_slot: 0, finalized_epoch: 0, finalized_root: 0x0000…0000, peers: 0, service: slot_notifier
+Jan 01 00:00:13.210 INFO New block received                      root: 0x1a2b…3c4d, slot: 1
+Jan 01 00:00:24.000 INFO Synced                                  slot: 2, block: 0x4ebc…7d1f, epoch: 0, finalized_epoch: 0, finalized_root: 0x0000…0000, exec_hash: 0x1234…5678 (verified), peers: 3, service: slot_notifier
+Jan 01 00:00:37.010 WARN Beacon chain re-org                     reorg_distance: 1, new_slot: 3, new_head: 0x9f…, previous_slot: 3, previous_head: 0x8e…, service: beacon
+Jan 01 00:00:48.000 INFO Synced                                  slot: 4, block: 0x5abc…7d1f, epoch: 0, finalized_epoch: 0, finalized_root: 0x0000…0000, exec_hash: 0x1234…5678 (verified), peers: 4, service: slot_notifier
+";
+
+const NODE1_VC: &str = "\
+Jan 01 00:00:36.050 INFO Successfully published block            slot: 3, attestations: 10, deposits: 0, service: block
+";
+
+const NODE1_BN: &str = "\
+Jan 01 00:00:48.000 INFO Synced                                  slot: 4, block: 0x5abc…7d1f, epoch: 0, finalized_epoch: 0, finalized_root: 0x0000…0000, exec_hash: 0x1234…5678 (verified), peers: 4, service: slot_notifier
+";
+
+const NODE1_GETH: &str = "\
+INFO [01-01|00:00:05.001] Starting Geth on Ethereum mainnet...
+INFO [01-01|00:00:13.350] Chain head was updated                   number=1 hash=0x1a2b..3c4d root=0x5e6f..7a8b elapsed=1.2ms
+INFO [01-01|00:00:37.100] Chain head was updated                   number=2 hash=0x9f00..0000 root=0x5e6f..7a8b elapsed=1.1ms
+";
+
+#[test]
+fn parse_lighthouse_lines() {
+    let lines: Vec<_> = NODE0_BN.lines().filter_map(parse_lighthouse_line).collect();
+    assert_eq!(lines.len(), 5);
+    assert_eq!(
+        lines[2],
+        ClEvent::Status {
+            time: 24.0,
+            head_slot: 2,
+            finalized_epoch: Some(0),
+            peers: Some(3),
+            synced: true,
+        }
+    );
+    assert_eq!(
+        lines[1],
+        ClEvent::BlockReceived {
+            time: 13.21,
+            slot: 1,
+            root: "0x1a2b…3c4d".to_string(),
+        }
+    );
+    assert_eq!(
+        lines[3],
+        ClEvent::Reorg {
+            time: 37.01,
+            previous_slot: 3,
+            new_slot: 3,
+            distance: Some(1),
+        }
+    );
+}
+
+#[test]
+fn parse_geth_lines() {
+    let heads: Vec<_> = NODE1_GETH.lines().filter_map(parse_geth_line).collect();
+    assert_eq!(
+        heads,
+        vec![
+            ElHead {
+                time: 13.35,
+                number: 1
+            },
+            ElHead {
+                time: 37.1,
+                number: 2
+            },
+        ]
+    );
+}
+
+#[test]
+fn analyze_data_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    let hosts = dir.path().join("shadow").join("hosts");
+    create_dir_all(hosts.join("node0")).unwrap();
+    create_dir_all(hosts.join("node1")).unwrap();
+    create_dir_all(dir.path().join("node1").join("geth")).unwrap();
+    write(hosts.join("node0").join("lighthouse.1001.stdout"), NODE0_BN).unwrap();
+    write(hosts.join("node1").join("lighthouse.1001.stdout"), NODE1_BN).unwrap();
+    write(hosts.join("node1").join("lighthouse.1002.stdout"), NODE1_VC).unwrap();
+    write(hosts.join("node1").join("geth.1000.stderr"), NODE1_GETH).unwrap();
+    write(
+        dir.path().join("node1").join("geth").join("geth.log"),
+        NODE1_GETH,
+    )
+    .unwrap();
+
+    let analysis = analyze(dir.path()).unwrap();
+    let health = analysis.health;
+    assert_eq!(health.highest_slot, 4);
+    // slot 1 was received, slot 3 published
+    assert_eq!(health.missed_slots, vec![2, 4]);
+    assert_eq!(health.finalized_epoch, Some(0));
+    assert_eq!(health.reorgs, 1);
+    assert_eq!(health.max_reorg_distance, Some(1));
+    assert_eq!(health.hosts["node0"].peers, Some(4));
+    assert_eq!(health.hosts["node1"].el_head, Some(2));
+
+    let analysis = dir.path().join("analysis");
+    let head_slots = read_to_string(analysis.join("head_slots.csv")).unwrap();
+    assert_eq!(head_slots.lines().count(), 5);
+    assert!(head_slots.contains("24,node0,2,0,3,true"));
+    let el_heads = read_to_string(analysis.join("el_heads.csv")).unwrap();
+    assert_eq!(el_heads.lines().count(), 3);
+    assert!(analysis.join("reorgs.csv").is_file());
+    assert!(analysis.join("summary.json").is_file());
+}
+
+#[test]
+fn invalid_utf8_in_logs_is_skipped() {
+    let dir = tempfile::tempdir().unwrap();
+    let hosts = dir.path().join("shadow").join("hosts");
+    create_dir_all(hosts.join("node0")).unwrap();
+    let mut log = b"\xff\xfe garbage\n".to_vec();
+    log.extend_from_slice(NODE0_BN.as_bytes());
+    write(hosts.join("node0").join("lighthouse.1001.stdout"), log).unwrap();
+
+    let analysis = analyze(dir.path()).unwrap();
+    assert_eq!(analysis.health.hosts["node0"].peers, Some(4));
+}
+
+const SHADOW_YAML: &str = "
+network:
+  graph:
+    type: gml
+    inline: |
+      graph [
+        directed 1
+        node [
+          id 0
+          label \"na-east-reliable\"
+        ]
+        node [
+          id 1
+          label \"europe-home\"
+        ]
+      ]
+hosts:
+  node0:
+    network_node_id: 0
+  node1:
+    network_node_id: 1
+";
+
+#[test]
+fn propagation_by_placement() {
+    let dir = tempfile::tempdir().unwrap();
+    let hosts = dir.path().join("shadow").join("hosts");
+    for (node, markers, log) in [
+        ("node0", ["na-east", "reliable"], NODE0_BN),
+        (
+            "node1",
+            ["europe", "home"],
+            "Jan 01 00:00:12.500 INFO New block received  root: 0x1a2b…3c4d, slot: 1\n\
+             Jan 01 00:00:12.900 INFO New block received  root: 0x1a2b…3c4d, slot: 1\n\
+             Jan 01 00:00:16.250 INFO Unaggregated attestation  validator: 7, src: gossip, slot: 1, epoch: 0, delay_ms: 4250, index: 0, head: 0x1a2b…3c4d, service: val_mon\n\
+             Jan 01 00:00:16.400 INFO Unaggregated attestation  validator: 7, src: gossip, slot: 1, epoch: 0, delay_ms: 4400, index: 0, head: 0x1a2b…3c4d, service: val_mon\n\
+             Jan 01 00:00:16.100 INFO Unaggregated attestation  validator: 8, src: api, slot: 1, epoch: 0, delay_ms: 4100, index: 0, head: 0x1a2b…3c4d, service: val_mon\n",
+        ),
+    ] {
+        create_dir_all(hosts.join(node)).unwrap();
+        create_dir_all(dir.path().join(node)).unwrap();
+        write(hosts.join(node).join("lighthouse.1001.stdout"), log).unwrap();
+        for marker in markers {
+            write(dir.path().join(node).join(marker), "").unwrap();
+        }
+    }
+    write(dir.path().join("shadow.yaml"), SHADOW_YAML).unwrap();
+    write(
+        dir.path().join("values.env"),
+        "export SLOT_DURATION_IN_SECONDS=\"12\"\n\
+         export GENESIS_TIMESTAMP=\"946684800\"\n\
+         export GENESIS_DELAY=\"0\"\n",
+    )
+    .unwrap();
+
+    let analysis = analyze(dir.path()).unwrap();
+    let propagation = analysis.propagation.unwrap();
+    let overall = propagation.overall.unwrap();
+    assert_eq!(overall.count, 2);
+    assert!((overall.min - 0.5).abs() < 1e-9);
+    assert!((overall.max - 1.21).abs() < 1e-9);
+    assert_eq!(
+        propagation.by_location.keys().collect::<Vec<_>>(),
+        vec!["europe", "na-east"]
+    );
+    assert_eq!(
+        propagation
+            .by_location_and_reliability
+            .keys()
+            .collect::<Vec<_>>(),
+        vec!["europe/home", "na-east/reliable"]
+    );
+    let arrivals = read_to_string(dir.path().join("analysis").join("block_arrivals.csv")).unwrap();
+    assert!(arrivals.contains("1,0x1a2b…3c4d,node1,europe,home,12.5,0.5"));
+
+    // only the first gossip arrival counts, attestations published via the API are ignored
+    let attestations = analysis.attestation_propagation.unwrap();
+    let overall = attestations.overall.unwrap();
+    assert_eq!(overall.count, 1);
+    assert!((overall.max - 4.25).abs() < 1e-9);
+    assert_eq!(
+        attestations.by_reliability.keys().collect::<Vec<_>>(),
+        vec!["home"]
+    );
+    let arrivals =
+        read_to_string(dir.path().join("analysis").join("attestation_arrivals.csv")).unwrap();
+    assert!(arrivals.contains("1,7,node1,europe,home,16.25,4.25"));
+}
+
+#[test]
+fn nearest_rank_percentiles() {
+    let values = (1..=100).map(f64::from).collect();
+    let result = percentiles(values).unwrap();
+    assert_eq!(result.p50, 50.0);
+    assert_eq!(result.p95, 95.0);
+    assert_eq!(result.max, 100.0);
+    assert!(percentiles(vec![]).is_none());
+}
+
+#[test]
+fn placements_from_manifest() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut boot = host(
+        dir.path(),
+        "node0boot",
+        Ipv4Addr::new(11, 0, 0, 1),
+        "geth_bootnode",
+    );
+    boot.network_node_id = 3;
+    boot.reliability = "reliable".to_string();
+    boot.tag = Some("boot".to_string());
+    boot.clients[0].ports = [("discovery".to_string(), 30305)].into_iter().collect();
+    boot.clients[0].enode = Some("enode://abcd@11.0.0.1:0?discport=30305".to_string());
+    let manifest = Manifest { hosts: vec![boot] };
+    let json = serde_json::to_string(&manifest).unwrap();
+    assert!(json.contains("\"type\":\"geth_bootnode\""));
+    assert!(!json.contains("\"enr\""));
+    write(dir.path().join(MANIFEST_FILE), json).unwrap();
+
+    let placements = read_placements(dir.path()).unwrap();
+    assert_eq!(
+        placements["node0boot"],
+        Placement {
+            location: "europe".to_string(),
+            reliability: "reliable".to_string(),
+        }
+    );
+}
@@ -0,0 +1,384 @@
+use ethshadow::config::ethshadow::{IpVersion, Subnet};
+use ethshadow::config::FullConfig;
+use ethshadow::Error;
+use std::path::Path;
+
+fn shadow_config(yaml: &str) -> Result<(), Error> {
+    let config: FullConfig = yaml.try_into()?;
+    config.shadow_config.validate()
+}
+
+#[test]
+fn valid_shadow_options() {
+    shadow_config(
+        "
+        general:
+          stop_time: 10 min
+          seed: 3
+          log_level: debug
+          progress: true
+        experimental:
+          runahead: 20ms
+        hosts:
+          custom:
+            location: europe
+            reliability: reliable
+            processes:
+              - path: /bin/sleep
+                args: [\"10\"]
+                start_time: 5
+        ",
+    )
+    .unwrap();
+}
+
+#[test]
+fn unknown_shadow_options_are_accepted() {
+    shadow_config(
+        "
+        general:
+          stop_tme: 10 min
+        some_future_section:
+          option: 1
+        ",
+    )
+    .unwrap();
+}
+
+#[test]
+fn invalid_shadow_options() {
+    let err = shadow_config("general:\n  log_level: verbose").unwrap_err();
+    assert!(matches!(err, Error::InvalidShadowOption(option, _) if option == "general.log_level"));
+    let err = shadow_config("general:\n  stop_time: soon").unwrap_err();
+    assert!(matches!(err, Error::InvalidTime(option, _) if option == "general.stop_time"));
+    let err = shadow_config("hosts:\n  custom:\n    processes:\n      - path: 1").unwrap_err();
+    assert!(
+        matches!(err, Error::InvalidShadowOption(option, _) if option == "hosts.custom.processes[0].path")
+    );
+}
+
+#[test]
+fn json_schema() {
+    let schema = ethshadow::config::json_schema();
+    assert_eq!(
+        schema["properties"]["ethereum"]["$ref"],
+        "#/definitions/EthShadowConfig"
+    );
+    let clients = schema["definitions"]["Client"]["oneOf"].as_array().unwrap();
+    assert!(clients
+        .iter()
+        .any(|client| { client["allOf"][1]["properties"]["type"]["const"] == "lighthouse" }));
+    let genesis = &schema["definitions"]["Genesis"]["properties"];
+    for fork in [
+        "capella",
+        "deneb",
+        "electra",
+        "fulu",
+        "osaka",
+        "gloas",
+        "amsterdam",
+    ] {
+        assert!(genesis[fork].is_object(), "{fork} missing in schema");
+    }
+}
+
+#[test]
+fn includes_and_merge_keys() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    std::fs::create_dir(dir.path().join("shared"))?;
+    std::fs::write(
+        dir.path().join("shared/locations.yaml"),
+        "
+        ethereum:
+          locations:
+            mars:
+              latency_to: &latencies
+                mars: 1s
+              packet_loss_to:
+                mars: 0.0
+            moon:
+              latency_to:
+                <<: *latencies
+                moon: 2ms
+              packet_loss_to:
+                moon: 0.0
+          validators: 10
+        ",
+    )?;
+    std::fs::write(
+        dir.path().join("base.yaml"),
+        "
+        include: shared/locations.yaml
+        general:
+          stop_time: 1h
+        ",
+    )?;
+    let config_path = dir.path().join("config.yaml");
+    std::fs::write(
+        &config_path,
+        "
+        include:
+          - base.yaml
+        general:
+          seed: 42
+        ethereum:
+          validators: 20
+        ",
+    )?;
+    let config: FullConfig = config_path.as_path().try_into()?;
+    let ethereum = &config.ethshadow_config;
+    assert_eq!(ethereum.validators, Some(20));
+    let moon = &ethereum.locations["moon"].latency_to;
+    assert_eq!(*moon["mars"], std::time::Duration::from_secs(1));
+    assert_eq!(*moon["moon"], std::time::Duration::from_millis(2));
+    assert_eq!(config.shadow_config.seed(), 42);
+    assert_eq!(
+        config.shadow_config.stop_time()?,
+        Some(std::time::Duration::from_secs(3600))
+    );
+    Ok(())
+}
+
+#[test]
+fn file_paths_are_relative_to_the_config() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    std::fs::create_dir(dir.path().join("shared"))?;
+    std::fs::write(
+        dir.path().join("shared/genesis.yaml"),
+        "
+        ethereum:
+          genesis:
+            alloc_files: [contracts.json]
+          clients:
+            monitoring:
+              type: prometheus
+              rule_files: [rules.yaml, /etc/rules.yaml]
+        ",
+    )?;
+    let config_path = dir.path().join("config.yaml");
+    std::fs::write(&config_path, "include: shared/genesis.yaml")?;
+    let config: FullConfig = config_path.as_path().try_into()?;
+    let shared = dir.path().canonicalize()?.join("shared");
+    assert_eq!(
+        config.ethshadow_config.genesis.alloc_files,
+        vec![shared.join("contracts.json")]
+    );
+    let loaded = ethshadow::config::load(&config_path)?;
+    let rule_files = &loaded["ethereum"]["clients"]["monitoring"]["rule_files"];
+    assert_eq!(
+        rule_files[0].as_str().map(Path::new),
+        Some(shared.join("rules.yaml").as_path())
+    );
+    assert_eq!(rule_files[1], "/etc/rules.yaml");
+    Ok(())
+}
+
+#[test]
+fn include_cycle() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("a.yaml"), "include: b.yaml")?;
+    std::fs::write(dir.path().join("b.yaml"), "include: a.yaml")?;
+    let result: Result<FullConfig, _> = dir.path().join("a.yaml").as_path().try_into();
+    assert!(matches!(result, Err(Error::IncludeCycle(_))));
+    Ok(())
+}
+
+#[test]
+fn env_var_substitution() -> Result<(), Error> {
+    std::env::set_var("ETHSHADOW_TEST_VALIDATORS", "64");
+    let config: FullConfig = "
+        general:
+          data_directory: ${ETHSHADOW_TEST_UNSET:-/tmp}/$${not_a_var}
+        ethereum:
+          validators: ${ETHSHADOW_TEST_VALIDATORS}
+    "
+    .try_into()?;
+    assert_eq!(config.ethshadow_config.validators, Some(64));
+    assert_eq!(
+        config.shadow_config.0["general"]["data_directory"],
+        "/tmp/${not_a_var}"
+    );
+
+    let result: Result<FullConfig, _> = "general:\n  seed: ${ETHSHADOW_TEST_UNSET}".try_into();
+    assert!(matches!(result, Err(Error::MissingEnvVar(name)) if name == "ETHSHADOW_TEST_UNSET"));
+    Ok(())
+}
+
+#[test]
+fn prometheus_options() -> Result<(), Error> {
+    let config: FullConfig = "
+        ethereum:
+          clients:
+            monitoring:
+              type: prometheus
+              scrape_interval: 1s
+              retention: 1d
+              rule_files: [rules.yaml]
+              extra_targets:
+                - job_name: custom
+                  targets: [\"100.0.0.1:8080\"]
+                  labels:
+                    role: sidecar
+              remote_write:
+                - url: http://localhost:9201/write
+            shard_europe:
+              type: prometheus
+              shard:
+                by: location
+                locations: [europe]
+            shard_hash:
+              type: prometheus
+              shard:
+                by: hash
+                count: 4
+                index: 1
+            aggregator:
+              type: prometheus
+              aggregate: true
+    "
+    .try_into()?;
+    assert!(config.ethshadow_config.clients.contains_key("monitoring"));
+    assert!(config.ethshadow_config.clients.contains_key("shard_hash"));
+
+    let schema = ethshadow::config::json_schema();
+    let prometheus = &schema["definitions"]["Prometheus"]["properties"];
+    assert_eq!(prometheus["scrape_interval"]["default"], "15s");
+    assert!(prometheus["extra_targets"].is_object());
+    assert!(schema["definitions"]["Shard"].is_object());
+    Ok(())
+}
+
+#[test]
+fn bootnodes_spread_over_locations() -> Result<(), Error> {
+    let mut config: FullConfig = "
+        ethereum:
+          nodes: 10
+          bootnodes:
+            count: 3
+            locations: [europe, na_east]
+            strategy: nearest_location
+    "
+    .try_into()?;
+    config.ethshadow_config.add_default_builtins();
+    let nodes = config.ethshadow_config.desugar_nodes()?;
+    let boot: Vec<_> = nodes
+        .iter()
+        .filter(|node| node.tag == Some("boot"))
+        .map(|node| (node.location, node.count))
+        .collect();
+    assert_eq!(boot, vec![("europe", 2), ("na_east", 1)]);
+    assert_eq!(
+        nodes
+            .iter()
+            .filter(|node| node.tag.is_none())
+            .map(|node| node.count)
+            .sum::<usize>(),
+        10
+    );
+    Ok(())
+}
+
+#[test]
+fn ip_versions() -> Result<(), Error> {
+    let config: FullConfig = "
+        ethereum:
+          addressing:
+            ip_version: dual_stack
+    "
+    .try_into()?;
+    assert_eq!(
+        config.ethshadow_config.addressing.ip_version,
+        IpVersion::DualStack
+    );
+
+    let result = ethshadow::generate(
+        "
+        ethereum:
+          addressing:
+            ip_version: ipv6
+        ",
+        &std::env::temp_dir().join("ethshadow-ipv6-only"),
+        false,
+    );
+    assert!(matches!(result, Err(Error::Ipv6OnlyUnsupported)));
+    Ok(())
+}
+
+#[test]
+fn subnet_prefix_lengths() -> Result<(), Error> {
+    let config = |subnet: &str| -> Result<FullConfig, Error> {
+        format!(
+            "
+            ethereum:
+              nodes:
+                - location: europe
+                  reliability: reliable
+                  subnet: {subnet}
+            "
+        )
+        .as_str()
+        .try_into()
+    };
+    for subnet in ["0.0.0.0/0", "44.0.0.0/7", "44.12.0.0/31", "44.12.0.0/32"] {
+        assert!(config(subnet).is_err(), "{subnet} was accepted");
+    }
+    config("44.0.0.0/8")?;
+    let subnet: Subnet = "44.0.0.0/8".parse().unwrap();
+    assert_eq!(subnet.host_count(), (1 << 24) - 2);
+    Ok(())
+}
+
+#[test]
+fn rewire_probability_range() {
+    let config = |probability: &str| -> Result<FullConfig, Error> {
+        format!("ethereum:\n  static_peering:\n    rewire_probability: {probability}\n")
+            .as_str()
+            .try_into()
+    };
+    for valid in ["0", "0.5", "1"] {
+        assert!(config(valid).is_ok(), "{valid} rejected");
+    }
+    for invalid in ["-0.1", "1.5", ".nan"] {
+        assert!(config(invalid).is_err(), "{invalid} accepted");
+    }
+}
+
+#[test]
+fn sybil_nodes() -> Result<(), Error> {
+    let mut config: FullConfig = "
+        ethereum:
+          clients:
+            sybil:
+              type: lighthouse_sybil
+              victims: victim
+              mode: beacon_node
+              target_peers: 100
+          nodes:
+            - location: europe
+              reliability: reliable
+              tag: victim
+            - location: east_asia
+              reliability: reliable
+              tag: sybil
+              subnet: 44.12.0.0/24
+              count:
+                total: 50
+              clients:
+                cl: sybil
+    "
+    .try_into()?;
+    config.ethshadow_config.add_default_builtins();
+    let nodes = config.ethshadow_config.desugar_nodes()?;
+    let sybils = nodes
+        .iter()
+        .find(|node| node.tag == Some("sybil"))
+        .expect("sybil group exists");
+    assert_eq!(sybils.count, 50);
+    assert_eq!(sybils.subnet, Some("44.12.0.0/24".parse().unwrap()));
+    assert_eq!(sybils.clients.len(), 1);
+    assert_eq!(sybils.clients[0].1.client_type(), "lighthouse_sybil");
+
+    let schema = ethshadow::config::json_schema();
+    assert!(schema["definitions"]["LighthouseSybil"].is_object());
+    Ok(())
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use common::host;
+use ethshadow::export::{export, file_name_for, ExportOptions};
+use ethshadow::manifest::{Manifest, MANIFEST_FILE};
+use std::fs::{create_dir_all, read_to_string, set_permissions, write, Permissions};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpListener};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::time::Duration;
+
+const FAKE_PROMETHEUS_ARGS: &str = "FAKE_PROMETHEUS_ARGS";
+const FAKE_PROMETHEUS_FAIL_ONCE: &str = "FAKE_PROMETHEUS_FAIL_ONCE";
+
+/// Wraps this test binary, so that the exporter runs [`fake_prometheus`] instead of Prometheus.
+fn fake_prometheus_executable(dir: &Path) -> String {
+    let exe = std::env::current_exe().unwrap();
+    let script = dir.join("prometheus.sh");
+    write(
+        &script,
+        format!(
+            "#!/bin/sh\n{FAKE_PROMETHEUS_ARGS}=\"$*\" exec \"{}\" fake_prometheus --exact --nocapture\n",
+            exe.display()
+        ),
+    )
+    .unwrap();
+    set_permissions(&script, Permissions::from_mode(0o755)).unwrap();
+    script.to_str().unwrap().to_string()
+}
+
+/// Not a test on its own: when invoked via [`fake_prometheus_executable`], this serves a single
+/// series for each query. The `instance` label is the IP of node1, so that the exporter has to
+/// add the host labels from the manifest. Like Prometheus, it writes to its TSDB.
+#[test]
+fn fake_prometheus() {
+    let Ok(args) = std::env::var(FAKE_PROMETHEUS_ARGS) else {
+        return;
+    };
+    if let Ok(marker) = std::env::var(FAKE_PROMETHEUS_FAIL_ONCE) {
+        if !Path::new(&marker).exists() {
+            write(&marker, "").unwrap();
+            eprintln!("listen tcp: bind: address already in use");
+            std::process::exit(1);
+        }
+    }
+    let arg = |name: &str| {
+        args.split(' ')
+            .find_map(|arg| arg.strip_prefix(name))
+            .unwrap()
+            .to_string()
+    };
+    write(
+        Path::new(&arg("--storage.tsdb.path=")).join("wal"),
+        "rewritten",
+    )
+    .unwrap();
+    let listener = TcpListener::bind(arg("--web.listen-address=")).unwrap();
+    for stream in listener.incoming() {
+        let mut stream = stream.unwrap();
+        let mut request = String::new();
+        BufReader::new(&stream).read_line(&mut request).unwrap();
+        let path = request.split(' ').nth(1).unwrap();
+        let body = match path.split_once('?') {
+            None => "ready".to_string(),
+            Some((_, query)) => {
+                let param = |name: &str| {
+                    query
+                        .split('&')
+                        .find_map(|param| param.strip_prefix(name))
+                        .unwrap()
+                };
+                let start: u64 = param("start=").parse().unwrap();
+                serde_json::json!({"status": "success", "data": {"resultType": "matrix", "result": [
+                    {"metric": {"__name__": param("query="), "instance": "11.0.0.2:32001"},
+                     "values": [[start, "1"], [start + 15, "2"]]}]}})
+                .to_string()
+            }
+        };
+        write!(stream, "HTTP/1.0 200 OK\r\n\r\n{body}").unwrap();
+    }
+}
+
+#[test]
+fn file_names_for_queries() {
+    assert_eq!(file_name_for("beacon_head_slot"), "beacon_head_slot");
+    assert_eq!(
+        file_name_for("rate(libp2p_peers{job=\"lighthouse\"}[1m])"),
+        "rate_libp2p_peers_job_lighthouse_1m"
+    );
+    assert_eq!(file_name_for("{}"), "query");
+}
+
+#[test]
+fn export_with_local_prometheus() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = Manifest {
+        hosts: vec![
+            host(
+                dir.path(),
+                "node0",
+                Ipv4Addr::new(11, 0, 0, 1),
+                "prometheus",
+            ),
+            host(
+                dir.path(),
+                "node1",
+                Ipv4Addr::new(11, 0, 0, 2),
+                "lighthouse",
+            ),
+        ],
+    };
+    write(
+        dir.path().join(MANIFEST_FILE),
+        serde_json::to_string(&manifest).unwrap(),
+    )
+    .unwrap();
+    write(
+        dir.path().join("shadow.yaml"),
+        "general:\n  stop_time: 30s\n",
+    )
+    .unwrap();
+    create_dir_all(dir.path().join("node0/prometheus")).unwrap();
+    write(dir.path().join("node0/prometheus/wal"), "simulation").unwrap();
+    // the first start fails as if someone else took the port
+    std::env::set_var(FAKE_PROMETHEUS_FAIL_ONCE, dir.path().join("failed_once"));
+
+    let options = ExportOptions {
+        queries: vec!["beacon_head_slot".to_string()],
+        step: Duration::from_secs(15),
+        output: dir.path().join("metrics"),
+        prometheus_executable: fake_prometheus_executable(dir.path()),
+    };
+    let files = export(dir.path(), &options).unwrap();
+    assert_eq!(files, vec![dir.path().join("metrics/beacon_head_slot.csv")]);
+
+    let csv = read_to_string(&files[0]).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("time,value,__name__,host,instance,location,reliability")
+    );
+    assert_eq!(
+        lines.next(),
+        Some("0,1,beacon_head_slot,node1,11.0.0.2:32001,europe,home")
+    );
+    assert_eq!(
+        lines.next(),
+        Some("15,2,beacon_head_slot,node1,11.0.0.2:32001,europe,home")
+    );
+    assert!(dir.path().join("failed_once").exists());
+    assert_eq!(
+        read_to_string(dir.path().join("node0/prometheus/wal")).unwrap(),
+        "simulation"
+    );
+}
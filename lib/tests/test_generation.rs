@@ -1,4 +1,8 @@
+mod common;
+
+use common::{generate_nodes, process_args};
 use std::error::Error;
+use std::fs::read_to_string;
 use tempfile::tempdir;
 
 #[test]
@@ -8,3 +12,66 @@ fn basic_config() -> Result<(), Box<dyn Error>> {
     // TODO: add some assertions here
     Ok(())
 }
+
+#[test]
+fn runahead_exceeds_latency() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let config = "
+        experimental:
+          runahead: 20ms
+        ethereum:
+          validators: 4
+    ";
+    let result = ethshadow::generate(config, dir.path(), true);
+    assert!(matches!(
+        result,
+        Err(ethshadow::Error::RunaheadTooLarge(..))
+    ));
+    Ok(())
+}
+
+#[test]
+fn observers_see_later_nodes() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let (shadow, manifest) = generate_nodes(
+        "
+        ethereum:
+          clients:
+            fake_geth:
+              type: geth
+              executable: \"true\"
+            spammer:
+              type: blobssss
+              private_key: \"0x01\"
+              min_per_slot: 1
+              max_per_slot: 2
+              start_time: 5m
+          nodes:
+            - location: europe
+              reliability: reliable
+              clients:
+                monitoring: prometheus
+                spammer: spammer
+            - location: na_east
+              reliability: reliable
+              count: { total: 3 }
+              clients:
+                el: fake_geth
+                cl: lighthouse
+        ",
+        dir.path(),
+    )?;
+
+    let prometheus = read_to_string(dir.path().join("node0/prometheus.yaml"))?;
+    for host in &manifest.hosts[1..] {
+        for port in [23001, 32001] {
+            assert!(prometheus.contains(&format!("{}:{port}", host.ip)));
+        }
+    }
+    let args = process_args(&shadow, "node0", "blobssss");
+    let rpcs = args.split_once("--rpcs ").unwrap().1;
+    for host in &manifest.hosts[1..] {
+        assert!(rpcs.contains(&format!("http://{}:22001", host.ip)));
+    }
+    Ok(())
+}
@@ -0,0 +1,211 @@
+use ethshadow::config::ethshadow::{ForkTime, Genesis};
+use std::error::Error;
+use std::fs::{create_dir, read_to_string, write};
+use std::process::Command;
+use std::time::Duration;
+use tempfile::tempdir;
+
+#[test]
+fn custom_chain_spec() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let genesis: Genesis = serde_yaml::from_str(
+        "
+        slot_duration_in_seconds: 4
+        genesis_fork_version: \"0x10000042\"
+        extra:
+          MIN_GENESIS_ACTIVE_VALIDATOR_COUNT: 64
+          SLOT_DURATION_IN_SECONDS: 8
+        ",
+    )?;
+    ethshadow::genesis::write_config(&genesis, 64, None, dir.path().to_path_buf())?;
+    let values = read_to_string(dir.path().join("values.env"))?;
+    assert!(values.contains("export SLOT_DURATION_IN_SECONDS=\"4\"\n"));
+    assert!(values.contains("export GENESIS_FORK_VERSION=\"0x10000042\"\n"));
+    assert!(values.contains("export MIN_GENESIS_ACTIVE_VALIDATOR_COUNT=\"64\"\n"));
+    // extra values come last, so they win when the file is sourced
+    let default = values.find("export SLOT_DURATION_IN_SECONDS=\"4\"");
+    let overridden = values.find("export SLOT_DURATION_IN_SECONDS=\"8\"");
+    assert!(default < overridden);
+    Ok(())
+}
+
+#[test]
+fn extra_values_are_sorted() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let genesis: Genesis = serde_yaml::from_str(
+        "
+        extra:
+          ZETA: 1
+          ALPHA: 2
+          MU: 3
+        ",
+    )?;
+    ethshadow::genesis::write_config(&genesis, 64, None, dir.path().to_path_buf())?;
+    let values = read_to_string(dir.path().join("values.env"))?;
+    let alpha = values.find("export ALPHA=\"2\"").unwrap();
+    let mu = values.find("export MU=\"3\"").unwrap();
+    let zeta = values.find("export ZETA=\"1\"").unwrap();
+    assert!(alpha < mu && mu < zeta);
+    Ok(())
+}
+
+#[test]
+fn zero_slot_duration_is_rejected() {
+    let result = serde_yaml::from_str::<Genesis>("slot_duration_in_seconds: 0");
+    assert!(result.is_err());
+    let result: Result<ethshadow::config::FullConfig, _> = "
+        ethereum:
+          genesis:
+            slot_duration_in_seconds: 0
+    "
+    .try_into();
+    assert!(result.is_err());
+}
+
+#[test]
+fn fork_times() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let genesis: Genesis = serde_yaml::from_str(
+        "
+        capella: 2
+        deneb: slot 320
+        electra: 20m
+        ",
+    )?;
+    assert_eq!(genesis.capella_epoch, Some(ForkTime::Epoch(2)));
+    assert_eq!(genesis.deneb_epoch, Some(ForkTime::Slot(320)));
+    // genesis is at 5m, so the fork is 15m or 2.34 epochs after genesis
+    assert_eq!(genesis.epoch_at(genesis.electra_epoch.unwrap()), 3);
+    ethshadow::genesis::write_config(
+        &genesis,
+        64,
+        Some(Duration::from_secs(7200)),
+        dir.path().to_path_buf(),
+    )?;
+    let values = read_to_string(dir.path().join("values.env"))?;
+    assert!(values.contains("export CAPELLA_FORK_EPOCH=\"2\"\n"));
+    assert!(values.contains("export DENEB_FORK_EPOCH=\"10\"\n"));
+    assert!(values.contains("export ELECTRA_FORK_EPOCH=\"3\"\n"));
+    assert!(values.contains("export FULU_FORK_EPOCH=\"99999999\"\n"));
+    Ok(())
+}
+
+#[test]
+fn fork_after_stop_time() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let mut genesis: Genesis = serde_yaml::from_str("electra: 2h")?;
+    let stop_time = Some(Duration::from_secs(3600));
+    let result =
+        ethshadow::genesis::write_config(&genesis, 64, stop_time, dir.path().to_path_buf());
+    assert!(matches!(
+        result,
+        Err(ethshadow::Error::ForkAfterStopTime(message)) if message.starts_with("Electra fork")
+    ));
+    assert!(!dir.path().join("values.env").exists());
+
+    genesis.allow_forks_after_stop_time = true;
+    ethshadow::genesis::write_config(&genesis, 64, stop_time, dir.path().to_path_buf())?;
+    let values = read_to_string(dir.path().join("values.env"))?;
+    assert!(values.contains("export ELECTRA_FORK_EPOCH=\"18\"\n"));
+    Ok(())
+}
+
+#[test]
+fn blob_schedule() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let genesis: Genesis = serde_yaml::from_str(
+        "
+        electra: 0
+        osaka: 1
+        blob_schedule:
+          - epoch: 2
+            max_blobs_per_block: 12
+            target_blobs_per_block: 9
+          - epoch: slot 96
+            max_blobs_per_block: 15
+        ",
+    )?;
+    ethshadow::genesis::write_config(&genesis, 64, None, dir.path().to_path_buf())?;
+    let values = read_to_string(dir.path().join("values.env"))?;
+    assert!(values.contains("export FULU_FORK_EPOCH=\"1\"\n"));
+    assert!(values.contains("export BPO_1_EPOCH=\"2\"\n"));
+    assert!(values.contains("export BPO_1_MAX_BLOBS=\"12\"\n"));
+    assert!(values.contains("export BPO_1_TARGET_BLOBS=\"9\"\n"));
+    assert!(values.contains("export BPO_2_EPOCH=\"3\"\n"));
+    assert!(values.contains("export BPO_2_MAX_BLOBS=\"15\"\n"));
+    assert!(!values.contains("BPO_2_TARGET_BLOBS"));
+    Ok(())
+}
+
+#[test]
+fn invalid_blob_schedules() -> Result<(), Box<dyn Error>> {
+    let schedules = [
+        // not increasing
+        "{ fulu: 1, blob_schedule: [{ epoch: 3, max_blobs_per_block: 12 }, \
+            { epoch: 3, max_blobs_per_block: 15 }] }",
+        // before fulu
+        "{ fulu: 4, blob_schedule: [{ epoch: 3, max_blobs_per_block: 12 }] }",
+        // fulu not scheduled
+        "{ blob_schedule: [{ epoch: 3, max_blobs_per_block: 12 }] }",
+        // more than the generator supports
+        "{ fulu: 1, blob_schedule: [{ epoch: 2, max_blobs_per_block: 12 }, \
+            { epoch: 3, max_blobs_per_block: 13 }, { epoch: 4, max_blobs_per_block: 14 }, \
+            { epoch: 5, max_blobs_per_block: 15 }, { epoch: 6, max_blobs_per_block: 16 }, \
+            { epoch: 7, max_blobs_per_block: 17 }] }",
+    ];
+    for schedule in schedules {
+        let dir = tempdir()?;
+        let genesis: Genesis = serde_yaml::from_str(schedule)?;
+        let result = ethshadow::genesis::write_config(&genesis, 64, None, dir.path().into());
+        assert!(
+            matches!(result, Err(ethshadow::Error::InvalidBlobSchedule(_))),
+            "{schedule}"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn preloaded_contracts() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let alloc_file = dir.path().join("alloc.json");
+    write(
+        &alloc_file,
+        r#"{
+            "0x4242424242424242424242424242424242424242": {"balance": "0x1", "nonce": "0x2"},
+            "0xca11bde05977b3631167028862be2a173976ca11": {"balance": "0x5"}
+        }"#,
+    )?;
+    let mut genesis: Genesis = serde_yaml::from_str(
+        "
+        alloc:
+          0xcA11bde05977b3631167028862bE2a173976CA11:
+            code: \"0x6080\"
+            storage:
+              \"0x00\": \"0x01\"
+            nonce: 1
+        ",
+    )?;
+    genesis.alloc_files.push(alloc_file);
+    let output = dir.path().join("out");
+    create_dir(&output)?;
+    ethshadow::genesis::write_config(&genesis, 64, None, output.clone())?;
+
+    // make sure the value survives being sourced by a shell
+    let sourced = Command::new("sh")
+        .arg("-c")
+        .arg(". ./values.env && printf %s \"$ADDITIONAL_PRELOADED_CONTRACTS\"")
+        .current_dir(&output)
+        .output()?;
+    let alloc: serde_json::Value = serde_json::from_slice(&sourced.stdout)?;
+    // the inline entry overrides the file entry, regardless of the case of the address
+    assert_eq!(
+        alloc["0xca11bde05977b3631167028862be2a173976ca11"],
+        serde_json::json!({"code": "0x6080", "storage": {"0x00": "0x01"}, "nonce": "0x1"})
+    );
+    assert_eq!(
+        alloc["0x4242424242424242424242424242424242424242"],
+        serde_json::json!({"balance": "0x1", "nonce": "0x2"})
+    );
+    Ok(())
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use common::host;
+use ethshadow::grafana::{generate_grafana, COMPOSE_FILE};
+use ethshadow::manifest::{Manifest, MANIFEST_FILE};
+use std::fs::{create_dir_all, read_to_string, write};
+use std::net::Ipv4Addr;
+
+#[test]
+fn dashboards_and_compose_file() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let manifest = Manifest {
+        hosts: vec![
+            host(
+                dir.path(),
+                "node0",
+                Ipv4Addr::new(11, 0, 0, 1),
+                "lighthouse",
+            ),
+            host(
+                dir.path(),
+                "node1",
+                Ipv4Addr::new(11, 0, 0, 2),
+                "prometheus",
+            ),
+        ],
+    };
+    write(
+        dir.path().join(MANIFEST_FILE),
+        serde_json::to_string(&manifest)?,
+    )?;
+    write(
+        dir.path().join("shadow.yaml"),
+        "general:\n  stop_time: 1h\n",
+    )?;
+    create_dir_all(dir.path().join("node1/prometheus"))?;
+
+    let output = dir.path().join("grafana");
+    generate_grafana(dir.path(), &output)?;
+
+    let dashboard: serde_json::Value =
+        serde_json::from_str(&read_to_string(output.join("dashboards/ethshadow.json"))?)?;
+    assert_eq!(dashboard["time"]["from"], "2000-01-01T00:00:00Z");
+    assert_eq!(dashboard["time"]["to"], "2000-01-01T01:00:00Z");
+    assert!(dashboard["panels"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|panel| panel["title"] == "Head slot"));
+
+    let datasources = read_to_string(output.join("provisioning/datasources/ethshadow.yaml"))?;
+    assert!(datasources.contains("http://prometheus-node1:9090"));
+
+    let compose: serde_yaml::Value =
+        serde_yaml::from_str(&read_to_string(output.join(COMPOSE_FILE))?)?;
+    let services = compose["services"].as_mapping().unwrap();
+    assert!(services.contains_key("grafana"));
+    assert!(services.contains_key("prometheus-node1"));
+    assert!(!services.contains_key("prometheus-node0"));
+    Ok(())
+}
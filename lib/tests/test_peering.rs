@@ -0,0 +1,321 @@
+mod common;
+
+use common::{generate_nodes, network_enr, process_args};
+use enr::k256::ecdsa::SigningKey;
+use enr::Enr;
+use ethshadow::config::ethshadow::{PeerTopology, StaticPeering};
+use ethshadow::config::ShadowConfig;
+use ethshadow::manifest::Manifest;
+use ethshadow::peering::{build_graph, NodeKey};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::fs::read_to_string;
+
+fn graph(topology: PeerTopology, locations: &[&str]) -> Vec<Vec<usize>> {
+    let config = StaticPeering {
+        topology,
+        degree: 4,
+        ..Default::default()
+    };
+    build_graph(&config, locations, &mut StdRng::seed_from_u64(1))
+}
+
+fn assert_symmetric(graph: &[Vec<usize>]) {
+    for (node, peers) in graph.iter().enumerate() {
+        assert!(!peers.contains(&node), "node {node} is its own peer");
+        for peer in peers {
+            assert!(
+                graph[*peer].contains(&node),
+                "{node} -> {peer} is one-sided"
+            );
+        }
+    }
+}
+
+#[test]
+fn random_regular_graph() {
+    let graph = graph(PeerTopology::RandomRegular, &["europe"; 50]);
+    assert_symmetric(&graph);
+    let degrees: usize = graph.iter().map(Vec::len).sum();
+    // a few nodes may end up with fewer peers, but none with more
+    assert!(graph.iter().all(|peers| peers.len() <= 4));
+    assert!(degrees >= 50 * 4 - 4);
+}
+
+#[test]
+fn small_world_graph() {
+    let graph = graph(PeerTopology::SmallWorld, &["europe"; 30]);
+    assert_symmetric(&graph);
+    assert!(graph.iter().all(|peers| peers.len() >= 2));
+}
+
+#[test]
+fn location_graph() {
+    let locations: Vec<&str> = (0..40)
+        .map(|idx| if idx % 2 == 0 { "europe" } else { "east_asia" })
+        .collect();
+    let graph = graph(PeerTopology::Location, &locations);
+    assert_symmetric(&graph);
+    for (node, peers) in graph.iter().enumerate() {
+        let same = peers
+            .iter()
+            .filter(|&&peer| locations[peer] == locations[node])
+            .count();
+        assert!(same >= 1);
+        assert!(
+            same < peers.len(),
+            "node {node} has no peer in another location"
+        );
+    }
+}
+
+#[test]
+fn small_graphs() {
+    assert_eq!(
+        graph(PeerTopology::RandomRegular, &[]),
+        Vec::<Vec<usize>>::new()
+    );
+    assert_eq!(
+        graph(PeerTopology::SmallWorld, &["europe"]),
+        vec![Vec::<usize>::new()]
+    );
+    assert_eq!(
+        graph(PeerTopology::RandomRegular, &["europe", "europe"]),
+        vec![vec![1], vec![0]]
+    );
+}
+
+#[test]
+fn node_key_identities() {
+    let key = NodeKey::random(&mut StdRng::seed_from_u64(1));
+    assert_eq!(key.enode_id().len(), 128);
+    // all secp256k1 peer ids share this prefix
+    assert!(key.peer_id().starts_with("16Uiu2HA"), "{}", key.peer_id());
+    assert_eq!(key.peer_id().len(), 53);
+}
+
+#[test]
+fn static_peers_per_client() -> Result<(), ethshadow::Error> {
+    let dir = tempfile::tempdir()?;
+    let (shadow, _) = generate_nodes(
+        "
+        ethereum:
+          static_peering:
+            degree: 2
+          clients:
+            fake_geth:
+              type: geth
+              executable: \"true\"
+          nodes:
+            - location: europe
+              reliability: reliable
+              count: { total: 4 }
+              clients:
+                el: fake_geth
+            - location: europe
+              reliability: reliable
+              clients:
+                el: fake_geth
+                el2: reth
+        ",
+        dir.path(),
+    )?;
+    let config = read_to_string(dir.path().join("node4/geth/static_peers.toml"))?;
+    let geth_peers: Vec<&str> = config
+        .lines()
+        .find_map(|line| line.strip_prefix("StaticNodes = ["))
+        .unwrap()
+        .trim_end_matches(']')
+        .split(", ")
+        .map(|peer| peer.trim_matches('"'))
+        .collect();
+    let reth_args = process_args(&shadow, "node4", "reth");
+    let reth_peers: Vec<&str> = reth_args
+        .split_once("--trusted-peers ")
+        .unwrap()
+        .1
+        .split_whitespace()
+        .next()
+        .unwrap()
+        .split(',')
+        .collect();
+    // both clients share the IP, but are separate peers in the graph
+    assert!(!geth_peers.is_empty());
+    assert!(!reth_peers.is_empty());
+    assert_ne!(geth_peers, reth_peers);
+    Ok(())
+}
+
+/// Boot hosts in europe (node0, node1) and na_east (node2), followed by geth nodes in west_asia
+/// (node3), na_west (node4) and south_africa (node5).
+fn bootnode_config(bootnodes: &str) -> String {
+    format!(
+        "
+        ethereum:
+          bootnodes:
+            {bootnodes}
+          clients:
+            fake_geth:
+              type: geth
+              executable: \"true\"
+          nodes:
+            - location: europe
+              reliability: reliable
+              count: {{ total: 2 }}
+              clients:
+                el: geth_bootnode
+            - location: na_east
+              reliability: reliable
+              clients:
+                el: geth_bootnode
+            - location: [west_asia, na_west, south_africa]
+              reliability: reliable
+              clients:
+                el: fake_geth
+        "
+    )
+}
+
+/// The hosts of the EL bootnodes passed to the geth of `host`.
+fn el_bootnode_hosts(shadow: &ShadowConfig, manifest: &Manifest, host: &str) -> Vec<String> {
+    let args = process_args(shadow, host, "true");
+    let bootnodes = args
+        .split_once("--bootnodes ")
+        .unwrap()
+        .1
+        .split_whitespace()
+        .next()
+        .unwrap();
+    let mut hosts: Vec<String> = bootnodes
+        .split(',')
+        .map(|enode| {
+            let ip = enode.split_once('@').unwrap().1.split_once(':').unwrap().0;
+            let host = manifest.hosts.iter().find(|h| h.ip.to_string() == ip);
+            host.unwrap().name.clone()
+        })
+        .collect();
+    hosts.sort();
+    hosts
+}
+
+#[test]
+fn nearest_location_bootnodes() -> Result<(), ethshadow::Error> {
+    let dir = tempfile::tempdir()?;
+    let (shadow, manifest) =
+        generate_nodes(&bootnode_config("strategy: nearest_location"), dir.path())?;
+    // west_asia and south_africa are closer to europe, na_west is closer to na_east
+    assert_eq!(
+        el_bootnode_hosts(&shadow, &manifest, "node3"),
+        ["node0", "node1"]
+    );
+    assert_eq!(el_bootnode_hosts(&shadow, &manifest, "node4"), ["node2"]);
+    assert_eq!(
+        el_bootnode_hosts(&shadow, &manifest, "node5"),
+        ["node0", "node1"]
+    );
+    Ok(())
+}
+
+#[test]
+fn random_bootnodes() -> Result<(), ethshadow::Error> {
+    let boot_hosts = ["node0", "node1", "node2"];
+    for per_client in [1, 2, 3, 5] {
+        let dir = tempfile::tempdir()?;
+        let (shadow, manifest) = generate_nodes(
+            &bootnode_config(&format!("{{ strategy: random, per_client: {per_client} }}")),
+            dir.path(),
+        )?;
+        for host in ["node3", "node4", "node5"] {
+            let selected = el_bootnode_hosts(&shadow, &manifest, host);
+            // at most all bootnodes, each once
+            assert_eq!(selected.len(), per_client.min(boot_hosts.len()));
+            assert!(selected.windows(2).all(|pair| pair[0] != pair[1]));
+            assert!(selected.iter().all(|h| boot_hosts.contains(&h.as_str())));
+        }
+
+        // the selection only depends on the seed
+        let again = tempfile::tempdir()?;
+        let (shadow_again, manifest_again) = generate_nodes(
+            &bootnode_config(&format!("{{ strategy: random, per_client: {per_client} }}")),
+            again.path(),
+        )?;
+        for host in ["node3", "node4", "node5"] {
+            assert_eq!(
+                el_bootnode_hosts(&shadow, &manifest, host),
+                el_bootnode_hosts(&shadow_again, &manifest_again, host),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The enode or the node id in the ENR of the given client of the first host. The signature of an
+/// ENR differs on each run, so the record itself can not be compared.
+fn identity(manifest: &Manifest, client_type: &str) -> Option<String> {
+    let client = manifest.hosts[0]
+        .clients
+        .iter()
+        .find(|client| client.client_type == client_type)?;
+    client.enode.clone().or_else(|| {
+        let enr: Enr<SigningKey> = client.enr.as_ref()?.parse().unwrap();
+        Some(hex::encode(enr.node_id().raw()))
+    })
+}
+
+#[test]
+fn bootnode_keys_follow_the_seed() -> Result<(), Box<dyn std::error::Error>> {
+    let generate =
+        |seed: u64| -> Result<(tempfile::TempDir, ShadowConfig, Manifest), ethshadow::Error> {
+            let dir = tempfile::tempdir()?;
+            let (shadow, manifest) = generate_nodes(
+                &format!(
+                    "
+                general:
+                  seed: {seed}
+                ethereum:
+                  nodes:
+                    - location: europe
+                      reliability: reliable
+                      clients:
+                        el: geth_bootnode
+                        cl: lighthouse_bootnode
+                    - location: europe
+                      reliability: reliable
+                      clients:
+                        cl: lighthouse
+                "
+                ),
+                dir.path(),
+            )?;
+            Ok((dir, shadow, manifest))
+        };
+    let (dir, shadow, manifest) = generate(1)?;
+    let boot = &manifest.hosts[0];
+
+    // the ENR is signed with the key the boot node reads from its network directory
+    let network_dir = dir.path().join("node0/lighthouse_bootnode");
+    let enr = network_enr(&network_dir);
+    assert_eq!(enr.ip4(), Some(boot.ip));
+    assert_eq!(enr.tcp4(), Some(4011));
+    assert_eq!(enr.udp4(), Some(4011));
+    assert_eq!(enr.ip6(), None);
+    let enr = enr.to_base64();
+    assert!(process_args(&shadow, "node1", "lighthouse").contains(&format!("--boot-nodes {enr}")));
+
+    // the enode belongs to the key of the geth bootnode
+    let secret = hex::decode(read_to_string(
+        dir.path().join("node0/geth_bootnode/boot.key"),
+    )?)?;
+    let key = NodeKey::from_secret_bytes(&secret.try_into().unwrap()).unwrap();
+    let enode = identity(&manifest, "geth_bootnode").unwrap();
+    assert!(enode.starts_with(&format!("enode://{}@", key.enode_id())));
+
+    // both are reproducible with the same seed only
+    let (_again, _, same_seed) = generate(1)?;
+    let (_other, _, other_seed) = generate(2)?;
+    for client in ["geth_bootnode", "lighthouse_bootnode"] {
+        assert_eq!(identity(&manifest, client), identity(&same_seed, client));
+        assert_ne!(identity(&manifest, client), identity(&other_seed, client));
+    }
+    Ok(())
+}
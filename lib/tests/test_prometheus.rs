@@ -0,0 +1,235 @@
+mod common;
+
+use common::{generate_nodes, process_args};
+use ethshadow::Error;
+use serde_yaml::Value;
+use std::fs::{create_dir, read_to_string, write};
+use std::path::Path;
+
+fn prometheus_yaml(dir: &Path, host: &str) -> Value {
+    serde_yaml::from_str(&read_to_string(dir.join(host).join("prometheus.yaml")).unwrap()).unwrap()
+}
+
+fn config(prometheus: &str) -> String {
+    format!(
+        "
+        ethereum:
+          clients:
+            monitoring:
+              type: prometheus
+              {prometheus}
+          nodes:
+            - location: europe
+              reliability: reliable
+              clients:
+                cl: lighthouse
+            - location: europe
+              reliability: reliable
+              clients:
+                monitoring: monitoring
+                receiver: remote_write_file
+        "
+    )
+}
+
+#[test]
+fn prometheus_settings() -> Result<(), Error> {
+    let dir = tempfile::tempdir()?;
+    let rules = dir.path().join("rules.yaml");
+    write(&rules, "groups: []\n")?;
+    let (shadow, _) = generate_nodes(
+        &config(&format!(
+            "scrape_interval: 1s
+              retention: 1d
+              rule_files: [\"{}\"]
+              remote_write:
+                - url: http://100.0.0.1:9201/write
+                  remote_timeout: 5s
+                  queue_config:
+                    max_shards: 1
+              remote_write_to_file: true",
+            rules.display()
+        )),
+        dir.path(),
+    )?;
+
+    let config = prometheus_yaml(dir.path(), "node1");
+    // the timeout must not exceed the interval
+    assert_eq!(config["global"]["scrape_interval"], "1s");
+    assert_eq!(config["global"]["scrape_timeout"], "1s");
+    let rule_file = config["rule_files"][0].as_str().unwrap();
+    assert_eq!(
+        Path::new(rule_file),
+        dir.path().join("node1/prometheus_rules/rules.yaml")
+    );
+    assert_eq!(read_to_string(rule_file)?, "groups: []\n");
+
+    let remote_write = config["remote_write"].as_sequence().unwrap();
+    assert_eq!(remote_write.len(), 2);
+    assert_eq!(remote_write[0]["remote_timeout"], "5s");
+    assert_eq!(remote_write[0]["queue_config"]["max_shards"], 1);
+    assert_eq!(remote_write[1]["url"], "http://localhost:9201/write");
+
+    let args = process_args(&shadow, "node1", "prometheus");
+    assert!(args.contains("--storage.tsdb.retention.time=1d"));
+    // the receiver runs the current executable
+    let exe = std::env::current_exe()?;
+    let receiver = process_args(&shadow, "node1", exe.to_str().unwrap());
+    assert!(receiver.starts_with("receive-remote-write --port 9201 --output"));
+    Ok(())
+}
+
+#[test]
+fn default_scrape_timeout() -> Result<(), Error> {
+    let dir = tempfile::tempdir()?;
+    let (shadow, _) = generate_nodes(&config("scrape_interval: 30s"), dir.path())?;
+    let config = prometheus_yaml(dir.path(), "node1");
+    assert!(config["global"].get("scrape_timeout").is_none());
+    assert!(config.get("rule_files").is_none());
+    assert!(config.get("remote_write").is_none());
+    let args = process_args(&shadow, "node1", "prometheus");
+    assert!(!args.contains("retention"));
+    Ok(())
+}
+
+#[test]
+fn invalid_rule_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let result = generate_nodes(&config("rule_files: [\"/\"]"), dir.path());
+    assert!(matches!(result, Err(Error::InvalidRuleFile(file)) if file == "/"));
+    let dir = tempfile::tempdir().unwrap();
+    for subdir in ["a", "b"] {
+        create_dir(dir.path().join(subdir)).unwrap();
+        write(dir.path().join(subdir).join("rules.yaml"), "groups: []\n").unwrap();
+    }
+    let result = generate_nodes(
+        &config(&format!(
+            "rule_files: [\"{0}/a/rules.yaml\", \"{0}/b/rules.yaml\"]",
+            dir.path().display()
+        )),
+        dir.path(),
+    );
+    assert!(matches!(result, Err(Error::DuplicateRuleFile(name)) if name == "rules.yaml"));
+}
+
+const SHARDED: &str = "
+ethereum:
+  clients:
+    by_location:
+      type: prometheus
+      shard: { by: location, locations: [europe] }
+    by_tag:
+      type: prometheus
+      shard: { by: tag, tags: [a] }
+    hash0:
+      type: prometheus
+      shard: { by: hash, count: 2, index: 0 }
+    hash1:
+      type: prometheus
+      shard: { by: hash, count: 2, index: 1 }
+    all:
+      type: prometheus
+      aggregate: true
+  nodes:
+    - location: europe
+      reliability: reliable
+      tag: a
+      clients:
+        cl: lighthouse
+    - location: na_east
+      reliability: reliable
+      count: { total: 3 }
+      clients:
+        cl: lighthouse
+    - location: europe
+      reliability: reliable
+      clients:
+        monitoring: by_location
+    - location: europe
+      reliability: reliable
+      clients:
+        monitoring: by_tag
+    - location: europe
+      reliability: reliable
+      clients:
+        monitoring: hash0
+    - location: europe
+      reliability: reliable
+      clients:
+        monitoring: hash1
+    - location: europe
+      reliability: reliable
+      clients:
+        monitoring: all
+";
+
+/// The hosts scraped by the Prometheus instance on the given host.
+fn scraped_hosts(dir: &Path, host: &str) -> Vec<String> {
+    let config = prometheus_yaml(dir, host);
+    let mut hosts: Vec<String> = config["scrape_configs"]
+        .as_sequence()
+        .unwrap()
+        .iter()
+        .flat_map(|job| job["static_configs"].as_sequence().unwrap())
+        .map(|target| target["labels"]["host"].as_str().unwrap().to_string())
+        .collect();
+    hosts.sort();
+    hosts
+}
+
+#[test]
+fn sharded_prometheus() -> Result<(), Error> {
+    let dir = tempfile::tempdir()?;
+    let (_, manifest) = generate_nodes(SHARDED, dir.path())?;
+    let ip = |host: &str| {
+        let record = manifest.hosts.iter().find(|record| record.name == host);
+        record.unwrap().ip
+    };
+
+    assert_eq!(scraped_hosts(dir.path(), "node4"), vec!["node0a"]);
+    assert_eq!(scraped_hosts(dir.path(), "node5"), vec!["node0a"]);
+    // every host is in exactly one hash shard
+    let hash0 = scraped_hosts(dir.path(), "node6");
+    let hash1 = scraped_hosts(dir.path(), "node7");
+    assert!(!hash0.is_empty() && !hash1.is_empty());
+    let mut all: Vec<String> = hash0.into_iter().chain(hash1).collect();
+    all.sort();
+    assert_eq!(all, vec!["node0a", "node1", "node2", "node3"]);
+
+    let aggregator = prometheus_yaml(dir.path(), "node8");
+    let jobs = aggregator["scrape_configs"].as_sequence().unwrap();
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0]["job_name"], "federate");
+    assert_eq!(jobs[0]["metrics_path"], "/federate");
+    assert_eq!(jobs[0]["honor_labels"], true);
+    assert_eq!(jobs[0]["params"]["match[]"][0], "{__name__=~\".+\"}");
+    let targets: Vec<String> = jobs[0]["static_configs"][0]["targets"]
+        .as_sequence()
+        .unwrap()
+        .iter()
+        .map(|target| target.as_str().unwrap().to_string())
+        .collect();
+    let shards: Vec<String> = ["node4", "node5", "node6", "node7"]
+        .iter()
+        .map(|host| format!("{}:9090", ip(host)))
+        .collect();
+    assert_eq!(targets, shards);
+    Ok(())
+}
+
+#[test]
+fn aggregate_without_shards() {
+    let dir = tempfile::tempdir().unwrap();
+    let result = generate_nodes(&config("aggregate: true"), dir.path());
+    assert!(matches!(result, Err(Error::NoPrometheusShards)));
+}
+
+#[test]
+fn invalid_hash_shard() {
+    let dir = tempfile::tempdir().unwrap();
+    let result = generate_nodes(
+        &config("shard: { by: hash, count: 2, index: 2 }"),
+        dir.path(),
+    );
+    assert!(matches!(result, Err(Error::InvalidShard(_))));
+}
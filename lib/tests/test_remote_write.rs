@@ -0,0 +1,135 @@
+use ethshadow::remote_write::{
+    decode, receive, Label, ProtoSample, Sample, TimeSeries, WriteRequest, MAX_REQUEST_SIZE,
+};
+use prost::Message;
+use std::fs::read_to_string;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+
+/// A compressed `WriteRequest` with a single series with the given labels and samples.
+fn write_request(labels: &[(&str, &str)], samples: &[(f64, i64)]) -> Vec<u8> {
+    let request = WriteRequest {
+        timeseries: vec![TimeSeries {
+            labels: labels
+                .iter()
+                .map(|(name, value)| Label {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                })
+                .collect(),
+            samples: samples
+                .iter()
+                .map(|&(value, timestamp)| ProtoSample { value, timestamp })
+                .collect(),
+        }],
+    };
+    snap::raw::Encoder::new()
+        .compress_vec(&request.encode_to_vec())
+        .unwrap()
+}
+
+/// Reads the status code of the response, skipping its headers.
+fn status(stream: &mut BufReader<TcpStream>) -> u16 {
+    let mut line = String::new();
+    stream.read_line(&mut line).unwrap();
+    let status = line.split(' ').nth(1).unwrap().parse().unwrap();
+    while line != "\r\n" {
+        line.clear();
+        stream.read_line(&mut line).unwrap();
+    }
+    status
+}
+
+#[test]
+fn decode_samples() {
+    let request = write_request(
+        &[("__name__", "up"), ("job", "geth")],
+        &[(1.0, 1000), (0.5, 2000)],
+    );
+    let samples = decode(&request).unwrap();
+    assert_eq!(samples.len(), 2);
+    assert_eq!(samples[0].labels["__name__"], "up");
+    assert_eq!(samples[0].labels["job"], "geth");
+    assert_eq!(samples[1].value, 0.5);
+    assert_eq!(samples[1].timestamp, 2000);
+    // not snappy
+    assert!(decode(&[0x0a, 0x05]).is_err());
+    // snappy, but not protobuf
+    let garbage = snap::raw::Encoder::new()
+        .compress_vec(&[0x0a, 0x05])
+        .unwrap();
+    assert!(decode(&garbage).is_err());
+    // claims to decompress to more than we accept
+    let mut bomb = vec![];
+    let mut len = MAX_REQUEST_SIZE as u64 + 1;
+    while len >= 0x80 {
+        bomb.push(len as u8 | 0x80);
+        len >>= 7;
+    }
+    bomb.push(len as u8);
+    assert!(decode(&bomb).is_err());
+}
+
+#[test]
+fn receive_to_file() {
+    const PORT: u16 = 39201;
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("remote_write.jsonl");
+    let file = output.clone();
+    thread::spawn(move || receive(PORT, &file));
+
+    let stream = (0..50)
+        .find_map(|_| {
+            TcpStream::connect(("127.0.0.1", PORT))
+                .inspect_err(|_| thread::sleep(Duration::from_millis(20)))
+                .ok()
+        })
+        .expect("receiver is listening");
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut stream = stream;
+    let body = write_request(&[("__name__", "beacon_head_slot")], &[(3.0, 946684812000)]);
+
+    // three requests on the same connection: with a content length, chunked, and too large
+    write!(
+        stream,
+        "POST /write HTTP/1.1\r\nHost: localhost\r\nContent-Encoding: snappy\r\nContent-Length: {}\r\n\r\n",
+        body.len()
+    )
+    .unwrap();
+    stream.write_all(&body).unwrap();
+    assert_eq!(status(&mut reader), 204);
+
+    write!(
+        stream,
+        "POST /write HTTP/1.1\r\nHost: localhost\r\nContent-Encoding: snappy\r\nTransfer-Encoding: chunked\r\n\r\n"
+    )
+    .unwrap();
+    for chunk in body.chunks(7) {
+        write!(stream, "{:x}\r\n", chunk.len()).unwrap();
+        stream.write_all(chunk).unwrap();
+        write!(stream, "\r\n").unwrap();
+    }
+    write!(stream, "0\r\n\r\n").unwrap();
+    assert_eq!(status(&mut reader), 204);
+
+    write!(
+        stream,
+        "POST /write HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n",
+        MAX_REQUEST_SIZE + 1
+    )
+    .unwrap();
+    assert_eq!(status(&mut reader), 400);
+
+    let lines: Vec<Sample> = read_to_string(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].labels["__name__"], "beacon_head_slot");
+    assert_eq!(lines[0].timestamp, 946684812000);
+    assert_eq!(lines[0].value, 3.0);
+    assert_eq!(lines[0], lines[1]);
+}
@@ -0,0 +1,72 @@
+use ethshadow::run::{parse_simulated_time, supervise, ProcessExit, SUMMARY_FILE};
+use ethshadow::ShadowInvocation;
+use std::fs::{create_dir_all, read_to_string, write};
+use std::time::Duration;
+
+#[test]
+fn simulated_time_from_shadow_log() {
+    assert_eq!(
+        parse_simulated_time(
+            "00:00:01.234567 [12:shadow-worker] 01:05:10.500000000 [INFO] [node1:1.0.0.1] [x] hi"
+        ),
+        Some(Duration::new(3910, 500_000_000))
+    );
+    assert_eq!(
+        parse_simulated_time("00:00:00.000012 [12:main] n/a [INFO] [n/a] [x] Starting"),
+        None
+    );
+    assert_eq!(parse_simulated_time("** Starting Shadow"), None);
+}
+
+#[test]
+fn summary_survives_invalid_utf8() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let invocation = ShadowInvocation::new(
+        "sh",
+        vec![
+            "-c".into(),
+            "printf 'garbage \\377\\n00:00:01.0 [1:main] 00:01:00.000000000 [INFO] hi\\n'".into(),
+        ],
+        Some(Duration::from_secs(120)),
+    );
+    let mut output = vec![];
+    let summary = supervise(invocation, dir.path(), &mut output)?;
+    assert!(summary.success);
+    assert_eq!(summary.simulated_seconds, 60.0);
+    assert!(output.starts_with(b"garbage \xff\n00:00:01.0"));
+    let written: serde_json::Value =
+        serde_json::from_str(&read_to_string(dir.path().join(SUMMARY_FILE))?)?;
+    assert_eq!(written["stop_time_seconds"], 120.0);
+    Ok(())
+}
+
+#[test]
+fn summary_of_failed_run() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let hosts = dir.path().join("shadow/hosts");
+    create_dir_all(hosts.join("node1"))?;
+    create_dir_all(hosts.join("node2"))?;
+    write(hosts.join("node1/geth.1000.exitcode"), "0\n")?;
+    write(hosts.join("node1/geth.1000.stdout"), "")?;
+    write(hosts.join("node2/lighthouse.1000.exitcode"), "1\n")?;
+    // the log text does not matter, only the exit status of Shadow
+    let invocation = ShadowInvocation::new(
+        "sh",
+        vec!["-c".into(), "echo 'all good'; exit 1".into()],
+        None,
+    );
+    let summary = supervise(invocation, dir.path(), std::io::sink())?;
+    assert!(!summary.success);
+    assert_eq!(summary.exit_code, Some(1));
+    assert_eq!(
+        summary.exited_processes,
+        [("node1", "geth.1000", 0), ("node2", "lighthouse.1000", 1)].map(
+            |(host, process, code)| ProcessExit {
+                host: host.to_string(),
+                process: process.to_string(),
+                exit_code: Some(code),
+            }
+        )
+    );
+    Ok(())
+}
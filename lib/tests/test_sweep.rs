@@ -0,0 +1,166 @@
+use ethshadow::config::FullConfig;
+use ethshadow::sweep::{expand, generate_sweep, MANIFEST_FILE};
+use ethshadow::Error;
+use serde_yaml::Value;
+use std::fs::{create_dir, read_to_string, write};
+
+#[test]
+fn sweep_expands_all_combinations() {
+    let config: Value = serde_yaml::from_str(
+        "
+        general:
+          stop_time: 10 min
+        ethereum:
+          validators: 50
+          nodes:
+            - locations: europe
+              reliabilities: reliable
+        sweep:
+          ethereum.validators: [100, 1000]
+          general.seed: [{range: [1, 3]}]
+          ethereum.nodes.0.reliabilities: [[reliable, home]]
+        ",
+    )
+    .unwrap();
+    let variants = expand(config).unwrap();
+    assert_eq!(variants.len(), 6);
+    assert_eq!(variants[0].name, "variant0");
+    assert_eq!(variants[5].name, "variant5");
+
+    let last = &variants[5].config;
+    assert!(last.get("sweep").is_none());
+    assert_eq!(last["ethereum"]["validators"], Value::from(1000));
+    assert_eq!(last["general"]["seed"], Value::from(3));
+    assert_eq!(last["general"]["stop_time"], Value::from("10 min"));
+    assert_eq!(
+        last["ethereum"]["nodes"][0]["reliabilities"],
+        serde_yaml::from_str::<Value>("[reliable, home]").unwrap()
+    );
+    assert_eq!(variants[5].parameters.len(), 3);
+}
+
+#[test]
+fn sweep_without_axes_yields_base_config() {
+    let config: Value = serde_yaml::from_str("ethereum:\n  validators: 10\n").unwrap();
+    let variants = expand(config.clone()).unwrap();
+    assert_eq!(variants.len(), 1);
+    assert_eq!(variants[0].config, config);
+}
+
+#[test]
+fn sweep_rejects_invalid_path() {
+    let config: Value = serde_yaml::from_str(
+        "
+        ethereum:
+          nodes: []
+        sweep:
+          ethereum.nodes.3.count: [1, 2]
+        ",
+    )
+    .unwrap();
+    assert!(matches!(expand(config), Err(Error::InvalidSweepPath(_))));
+}
+
+#[test]
+fn sweep_is_rejected_by_plain_generation() {
+    let result: Result<FullConfig, _> = "sweep:\n  general.seed: [1, 2]\n".try_into();
+    assert!(matches!(result, Err(Error::UnexpectedSweep)));
+}
+
+#[test]
+fn sweep_ranges_need_the_marker() {
+    let config: Value = serde_yaml::from_str(
+        "
+        sweep:
+          general.seed: [\"1..3\", {range: [-1, 1]}]
+        ",
+    )
+    .unwrap();
+    let seeds: Vec<Value> = expand(config)
+        .unwrap()
+        .iter()
+        .map(|variant| variant.config["general"]["seed"].clone())
+        .collect();
+    let expected = ["\"1..3\"", "-1", "0", "1"].map(|v| serde_yaml::from_str::<Value>(v).unwrap());
+    assert_eq!(seeds, expected);
+}
+
+#[test]
+fn sweep_rejects_invalid_ranges() {
+    for range in [
+        "{range: [1, 1000000]}",
+        "{range: [3, 1]}",
+        "{range: [1]}",
+        "{range: [a, b]}",
+        "{range: [1, 2], step: 1}",
+    ] {
+        let config: Value =
+            serde_yaml::from_str(&format!("sweep:\n  general.seed: [{range}]\n")).unwrap();
+        assert!(
+            matches!(expand(config), Err(Error::InvalidSweepRange(_))),
+            "{range} accepted"
+        );
+    }
+}
+
+/// Both variants use a client that does not exist, so generation fails after creating the
+/// variant directory.
+fn failing_sweep() -> Value {
+    serde_yaml::from_str(
+        "
+        general:
+          stop_time: 1h
+        ethereum:
+          nodes:
+            - locations: europe
+              reliabilities: reliable
+        sweep:
+          ethereum.nodes.0.clients: [{el: missing_el}, {el: other_el}]
+        ",
+    )
+    .unwrap()
+}
+
+#[test]
+fn sweep_failures_can_be_rerun() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("data");
+    for _ in 0..2 {
+        let result = generate_sweep(failing_sweep(), &output, false);
+        assert!(matches!(result, Err(Error::UnknownClient(_))));
+        assert!(!output.join("variant0").exists());
+        assert_eq!(read_to_string(output.join(MANIFEST_FILE)).unwrap(), "[]");
+    }
+}
+
+#[test]
+fn sweep_skips_generated_variants() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("data");
+    create_dir(&output).unwrap();
+    create_dir(output.join("variant0")).unwrap();
+    write(output.join("variant0/done"), "").unwrap();
+    let manifest = |client: &str| {
+        format!(
+            r#"[{{"name": "variant0", "directory": "{}", "parameters": {{"ethereum.nodes.0.clients": {{"el": "{client}"}}}}, "shadow_command": []}}]"#,
+            output.join("variant0").display()
+        )
+    };
+
+    write(output.join(MANIFEST_FILE), manifest("missing_el")).unwrap();
+    let result = generate_sweep(failing_sweep(), &output, false);
+    assert!(matches!(result, Err(Error::UnknownClient(client)) if client == "other_el"));
+    assert!(output.join("variant0/done").exists());
+    assert!(!output.join("variant1").exists());
+
+    write(output.join(MANIFEST_FILE), manifest("other_el")).unwrap();
+    let result = generate_sweep(failing_sweep(), &output, false);
+    assert!(matches!(result, Err(Error::SweepChanged(name)) if name == "variant0"));
+}
+
+#[test]
+fn sweep_refuses_foreign_directories() {
+    let dir = tempfile::tempdir().unwrap();
+    let result = generate_sweep(failing_sweep(), dir.path(), false);
+    assert!(matches!(result, Err(Error::OutputFolderExists)));
+}
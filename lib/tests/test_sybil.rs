@@ -0,0 +1,148 @@
+mod common;
+
+use common::{generate_nodes, process_args};
+use ethshadow::config::ShadowConfig;
+use ethshadow::manifest::Manifest;
+use ethshadow::Error;
+
+/// Two regular nodes (node0, node1), two victims (node2victim, node3victim), a regular bootnode
+/// (node4boot) and two sybils (node5sybil, node6sybil).
+fn sybil_config(sybil: &[&str]) -> String {
+    let sybil = sybil.join("\n              ");
+    format!(
+        "
+        ethereum:
+          clients:
+            sybil:
+              type: lighthouse_sybil
+              victims: victim
+              {sybil}
+          nodes:
+            - location: europe
+              reliability: reliable
+              count: {{ total: 2 }}
+              clients:
+                cl: lighthouse
+            - location: europe
+              reliability: reliable
+              tag: victim
+              count: {{ total: 2 }}
+              clients:
+                cl: lighthouse
+            - location: europe
+              reliability: reliable
+              tag: boot
+              clients:
+                cl: lighthouse_bootnode
+            - location: east_asia
+              reliability: reliable
+              tag: sybil
+              subnet: 44.12.0.0/24
+              count: {{ total: 2 }}
+              clients:
+                cl: sybil
+        "
+    )
+}
+
+/// The ENRs in the `--boot-nodes` argument of the Lighthouse of `host`.
+fn boot_nodes(shadow: &ShadowConfig, host: &str) -> Vec<String> {
+    let args = process_args(shadow, host, "lighthouse");
+    let enrs = args.split_once("--boot-nodes ").unwrap().1;
+    let enrs = enrs.split_whitespace().next().unwrap();
+    enrs.split(',').map(str::to_string).collect()
+}
+
+/// The ENR the given host registered in the manifest.
+fn enr(manifest: &Manifest, host: &str) -> String {
+    let host = manifest.hosts.iter().find(|h| h.name == host).unwrap();
+    host.clients[0].enr.clone().unwrap()
+}
+
+#[test]
+fn exclusive_sybil_bootnodes() -> Result<(), Error> {
+    let dir = tempfile::tempdir()?;
+    let (shadow, manifest) = generate_nodes(&sybil_config(&["exclusive: true"]), dir.path())?;
+    let sybils = vec![enr(&manifest, "node5sybil"), enr(&manifest, "node6sybil")];
+    let regular = vec![enr(&manifest, "node4boot")];
+    for victim in ["node2victim", "node3victim"] {
+        assert_eq!(boot_nodes(&shadow, victim), sybils);
+    }
+    for host in ["node0", "node1"] {
+        assert_eq!(boot_nodes(&shadow, host), regular);
+    }
+    Ok(())
+}
+
+#[test]
+fn non_exclusive_sybil_bootnodes() -> Result<(), Error> {
+    let dir = tempfile::tempdir()?;
+    // lcli is no longer needed, but older configs still set it
+    let (shadow, manifest) = generate_nodes(
+        &sybil_config(&["exclusive: false", "lcli_executable: lcli"]),
+        dir.path(),
+    )?;
+    // the sybils come first, so that the victims start discovery with them
+    let with_sybils = vec![
+        enr(&manifest, "node5sybil"),
+        enr(&manifest, "node6sybil"),
+        enr(&manifest, "node4boot"),
+    ];
+    for victim in ["node2victim", "node3victim"] {
+        assert_eq!(boot_nodes(&shadow, victim), with_sybils);
+    }
+    for host in ["node0", "node1"] {
+        assert_eq!(boot_nodes(&shadow, host), [enr(&manifest, "node4boot")]);
+    }
+    Ok(())
+}
+
+#[test]
+fn beacon_node_sybil_args() -> Result<(), Error> {
+    let dir = tempfile::tempdir()?;
+    let (shadow, manifest) = generate_nodes(
+        &sybil_config(&["mode: beacon_node", "target_peers: 7"]),
+        dir.path(),
+    )?;
+    let victim_ips: Vec<String> = manifest
+        .hosts
+        .iter()
+        .filter(|host| host.tag.as_deref() == Some("victim"))
+        .map(|host| host.ip.to_string())
+        .collect();
+    assert_eq!(victim_ips.len(), 2);
+
+    for sybil in ["node5sybil", "node6sybil"] {
+        let args = process_args(&shadow, sybil, "lighthouse");
+        assert!(args.contains(" beacon_node "));
+        // Lighthouse does not start without execution endpoint, even if nothing listens on it
+        assert!(args.contains(&format!(
+            "--execution-endpoint http://localhost:21001 --execution-jwt \"{}\"",
+            dir.path().join("jwt/jwtsecret").display()
+        )));
+        assert!(args.contains("--target-peers 7"));
+
+        let addresses = args.split_once("--libp2p-addresses ").unwrap().1;
+        let addresses: Vec<&str> = addresses
+            .split_whitespace()
+            .next()
+            .unwrap()
+            .split(',')
+            .collect();
+        assert_eq!(addresses.len(), 2);
+        for (address, ip) in addresses.iter().zip(&victim_ips) {
+            assert!(address.starts_with(&format!("/ip4/{ip}/tcp/")), "{address}");
+        }
+        let peer_ids = args.split_once("--trusted-peers ").unwrap().1;
+        let peer_ids = peer_ids.split_whitespace().next().unwrap().split(',');
+        for (peer_id, address) in peer_ids.zip(&addresses) {
+            assert!(address.ends_with(&format!("/p2p/{peer_id}")));
+        }
+    }
+    // in beacon node mode, the victims keep their regular bootnodes
+    assert_eq!(
+        boot_nodes(&shadow, "node2victim"),
+        [enr(&manifest, "node4boot")]
+    );
+    Ok(())
+}